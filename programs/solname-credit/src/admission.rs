@@ -0,0 +1,44 @@
+//! Collateral admission checks for SNS domain registry accounts.
+
+use anchor_lang::prelude::*;
+use crate::constants::{NAME_SERVICE_ID, NAME_TOKENIZER_ID, SOL_TLD_ROOT, REGISTRAR_SEED};
+use crate::error::ErrorCode;
+use crate::sns::NameRecordHeader;
+
+/// Checks that `domain_registry` is a plain, untokenized second-level `.sol`
+/// domain owned by `owner`, and returns its header. A non-default class is
+/// an extra authority over the domain, so it is refused like a registrar.
+pub fn admit_domain(domain_registry: &AccountInfo, owner: &Pubkey) -> Result<NameRecordHeader> {
+    require_keys_eq!(*domain_registry.owner, NAME_SERVICE_ID, ErrorCode::DomainNotNameService);
+    let header = NameRecordHeader::load(domain_registry)?;
+
+    let (tokenizer_state, _) = Pubkey::find_program_address(&[NAME_TOKENIZER_ID.as_ref()], &NAME_TOKENIZER_ID);
+    require_keys_neq!(header.owner, tokenizer_state, ErrorCode::DomainIsTokenized);
+    require_keys_eq!(header.parent_name, SOL_TLD_ROOT, ErrorCode::DomainIsSubdomain);
+    require_keys_eq!(header.class, Pubkey::default(), ErrorCode::DomainClassNotDefault);
+    require_keys_eq!(header.owner, *owner, ErrorCode::InvalidDomainOwner);
+
+    Ok(header)
+}
+
+/// Checks that `domain_registry` is still a name service account owned by `escrow`.
+pub fn check_escrowed(domain_registry: &AccountInfo, escrow: &Pubkey) -> Result<()> {
+    require_keys_eq!(*domain_registry.owner, NAME_SERVICE_ID, ErrorCode::DomainNotInEscrow);
+    let header = NameRecordHeader::load(domain_registry)?;
+    require_keys_eq!(header.owner, *escrow, ErrorCode::DomainNotInEscrow);
+    Ok(())
+}
+
+/// Rejects a domain with a subdomain registrar: while one exists its
+/// authority keeps control over the domain's subdomains after escrow.
+/// `registrar` is the client-supplied registrar PDA. Any account at that
+/// address counts as a registrar, and nothing is admitted until an admin has
+/// configured the registrar program.
+pub fn check_no_registrar(domain: &Pubkey, registrar_program: &Pubkey, registrar: Option<&AccountInfo>) -> Result<()> {
+    require_keys_neq!(*registrar_program, Pubkey::default(), ErrorCode::RegistrarNotConfigured);
+    let registrar = registrar.ok_or(ErrorCode::RegistrarAccountMismatch)?;
+    let (expected, _) = Pubkey::find_program_address(&[REGISTRAR_SEED, domain.as_ref()], registrar_program);
+    require_keys_eq!(registrar.key(), expected, ErrorCode::RegistrarAccountMismatch);
+    require!(registrar.lamports() == 0, ErrorCode::DomainHasRegistrar);
+    Ok(())
+}
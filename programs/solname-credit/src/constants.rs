@@ -1,6 +1,42 @@
+use anchor_lang::prelude::*;
+
 pub const GLOBAL_SEED: &[u8] = b"global";
 pub const POOL_SEED: &[u8] = b"pool";
 pub const LOAN_SEED: &[u8] = b"loan";
 pub const OFFER_SEED: &[u8] = b"offer";
 pub const AUCTION_SEED: &[u8] = b"auction";
 pub const VAULT_SEED: &[u8] = b"vault";
+pub const REQUEST_SEED: &[u8] = b"request";
+pub const NONCE_SEED: &[u8] = b"nonce";
+pub const DELEGATE_SEED: &[u8] = b"delegate";
+pub const CLAIM_SEED: &[u8] = b"claim";
+pub const LISTING_SEED: &[u8] = b"listing";
+pub const LENDER_SEED: &[u8] = b"lender";
+/// Seed of the subdomain registrar PDA, `[REGISTRAR_SEED, domain]` under the registrar program.
+pub const REGISTRAR_SEED: &[u8] = b"registrar";
+
+/// Extra domains a basket loan can escrow besides its primary domain.
+pub const MAX_BASKET_DOMAINS: usize = 4;
+
+/// Most record accounts a loan can snapshot at setup.
+pub const MAX_SNAPSHOT_RECORDS: usize = 4;
+
+/// Domain separator prepended to signed offer messages.
+pub const SIGNED_OFFER_PREFIX: &[u8] = b"solease:signed-offer:v1";
+
+/// `due_ts` of a callable loan that has not been called yet.
+pub const OPEN_ENDED_DUE_TS: i64 = i64::MAX;
+
+/// MVP pool loan policy.
+pub const POOL_LOAN_PRINCIPAL: u64 = 10_000_000; // 10 USDC
+pub const POOL_LOAN_DURATION: i64 = 86400 * 14; // 14 days
+pub const POOL_APR_BPS: u16 = 1000; // 10%
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+pub const SECONDS_PER_YEAR: u128 = 365 * 86_400;
+
+pub const NAME_SERVICE_ID: Pubkey = anchor_lang::solana_program::pubkey!("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX");
+/// Parent of every second-level `.sol` domain.
+pub const SOL_TLD_ROOT: Pubkey = anchor_lang::solana_program::pubkey!("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx");
+/// Program that wraps domains into NFTs.
+pub const NAME_TOKENIZER_ID: Pubkey = anchor_lang::solana_program::pubkey!("nftD3vbNkNqfj2Sd3HZwbpw4BxxKWr4AjGb9X38JeZk");
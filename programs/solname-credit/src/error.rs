@@ -28,4 +28,72 @@ pub enum ErrorCode {
     BidTooLow,
     #[msg("Auction ended.")]
     AuctionEnded,
+    #[msg("Invalid offer terms.")]
+    InvalidOfferTerms,
+    #[msg("Amount exceeds offer capacity.")]
+    OfferCapacityExceeded,
+    #[msg("Offer account does not match loan.")]
+    OfferMismatch,
+    #[msg("Loan request expired.")]
+    RequestExpired,
+    #[msg("Rate exceeds the request maximum.")]
+    RateAboveRequestMax,
+    #[msg("Destination is not owned by the loan creditor.")]
+    CreditorMismatch,
+    #[msg("Missing or invalid ed25519 signature for signed offer.")]
+    InvalidOfferSignature,
+    #[msg("Offer is restricted to a different domain.")]
+    OfferDomainMismatch,
+    #[msg("Offer nonce has been invalidated by the lender.")]
+    NonceInvalidated,
+    #[msg("Destination is not the loan creditor's vault.")]
+    InvalidPayoffDestination,
+    #[msg("Rollover limit reached.")]
+    RolloverNotAllowed,
+    #[msg("Payment covers the full balance; use repay.")]
+    PaymentCoversBalance,
+    #[msg("Invalid amount.")]
+    InvalidAmount,
+    #[msg("Loan has no installment schedule.")]
+    NoInstallmentSchedule,
+    #[msg("Loan is not callable or was already called.")]
+    LoanNotCallable,
+    #[msg("Missing or invalid loan claim token account.")]
+    InvalidLoanClaim,
+    #[msg("Listing is no longer valid for this loan.")]
+    ListingInvalid,
+    #[msg("Loan does not meet pool buyout eligibility.")]
+    BuyoutNotEligible,
+    #[msg("Domain registry is not owned by the name service program.")]
+    DomainNotNameService,
+    #[msg("Domain is a subdomain, not a .sol domain.")]
+    DomainIsSubdomain,
+    #[msg("Domain record class is not default.")]
+    DomainClassNotDefault,
+    #[msg("Subdomain registrar account missing or mismatched.")]
+    RegistrarAccountMismatch,
+    #[msg("Domain has a subdomain registrar.")]
+    DomainHasRegistrar,
+    #[msg("Subdomain registrar program is not configured.")]
+    RegistrarNotConfigured,
+    #[msg("Domain is not owned by the loan escrow.")]
+    DomainNotInEscrow,
+    #[msg("Account is not the domain's SOL record.")]
+    SolRecordMismatch,
+    #[msg("Account is not the requested domain record.")]
+    RecordMismatch,
+    #[msg("Record write is out of bounds or malformed.")]
+    InvalidRecordWrite,
+    #[msg("Too many record accounts to snapshot.")]
+    TooManySnapshotRecords,
+    #[msg("Domain or record contents differ from the setup snapshot.")]
+    RecordSnapshotMismatch,
+    #[msg("Loan basket is full.")]
+    BasketFull,
+    #[msg("Domain is not part of the loan's collateral.")]
+    BasketMismatch,
+    #[msg("Token account mint does not match the loan.")]
+    MintMismatch,
+    #[msg("Loan was created from a request; use cancel_request.")]
+    LoanHasRequest,
 }
@@ -18,11 +18,34 @@ pub fn init_global(ctx: Context<InitGlobal>, params: GlobalParams) -> Result<()>
     let global_state = &mut ctx.accounts.global_state;
     global_state.admin = ctx.accounts.admin.key();
     global_state.usdc_mint = ctx.accounts.usdc_mint.key();
+    apply_params(global_state, &params);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalParams<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [GLOBAL_SEED], bump, has_one = admin)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Replaces every protocol parameter set by `init_global`. Minimum interest
+/// already booked on a loan is kept; limits read when used, such as
+/// `max_pool_rollovers`, apply to open loans as well.
+pub fn update_global_params(ctx: Context<UpdateGlobalParams>, params: GlobalParams) -> Result<()> {
+    apply_params(&mut ctx.accounts.global_state, &params);
+    Ok(())
+}
+
+fn apply_params(global_state: &mut GlobalState, params: &GlobalParams) {
     global_state.global_cap = params.global_cap;
     global_state.grace_period_seconds = params.grace_period_seconds;
     global_state.min_bid_increment_bps = params.min_bid_increment_bps;
     global_state.auction_duration_seconds = params.auction_duration_seconds;
-    Ok(())
+    global_state.max_pool_rollovers = params.max_pool_rollovers;
+    global_state.pool_min_interest_seconds = params.pool_min_interest_seconds;
+    global_state.pool_origination_interest_bps = params.pool_origination_interest_bps;
+    global_state.pool_buyout_max_amount = params.pool_buyout_max_amount;
 }
 
 #[derive(Accounts)]
@@ -71,3 +94,17 @@ pub fn init_pool(ctx: Context<InitPool>) -> Result<()> {
     pool_state.bump = ctx.bumps.pool_state;
     Ok(())
 }
+
+#[derive(Accounts)]
+pub struct SetSubdomainRegistrar<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [GLOBAL_SEED], bump, has_one = admin)]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Sets the subdomain registrar program checked at admission. Until it is
+/// set, admission fails closed.
+pub fn set_subdomain_registrar(ctx: Context<SetSubdomainRegistrar>, program_id: Pubkey) -> Result<()> {
+    ctx.accounts.global_state.subdomain_registrar_program = program_id;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use crate::state::{LoanAccount, LoanStatus, LoanType, GlobalState, OfferAccount, RecordSnapshot};
+use crate::constants::{GLOBAL_SEED, LOAN_SEED, NAME_SERVICE_ID};
+use crate::error::ErrorCode;
+use crate::sns;
+use crate::admission::{admit_domain, check_no_registrar};
+use crate::instructions::claim::check_lender_signer;
+use crate::instructions::record::{escrow_records, return_records};
+
+#[derive(Accounts)]
+pub struct AddBasketDomain<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::SetupPending
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: Validated manualy via data inspection
+    #[account(mut, owner = NAME_SERVICE_ID @ ErrorCode::DomainNotNameService)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: PDA owning the loan's domains
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Name Service Program
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+    // remaining_accounts[0]: subdomain registrar PDA of the domain.
+}
+
+/// Escrows another domain under a loan that has not been funded yet, turning
+/// it into a basket loan.
+pub fn add_basket_domain(ctx: Context<AddBasketDomain>) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    require!(loan.principal_amount == 0, ErrorCode::LoanAlreadyActive);
+
+    admit_domain(&ctx.accounts.domain_registry, &ctx.accounts.borrower.key())?;
+    check_no_registrar(
+        &ctx.accounts.domain_registry.key(),
+        &ctx.accounts.global_state.subdomain_registrar_program,
+        ctx.remaining_accounts.first(),
+    )?;
+    loan.add_to_basket(ctx.accounts.domain_registry.key())?;
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        &[],
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SwapCollateral<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::Active
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: Escrowed domain to release; must be held by this loan
+    #[account(mut, constraint = loan_account.holds_domain(released_domain.key) @ ErrorCode::BasketMismatch)]
+    pub released_domain: AccountInfo<'info>,
+
+    /// CHECK: Replacement domain; validated manualy via data inspection
+    #[account(mut, owner = NAME_SERVICE_ID @ ErrorCode::DomainNotNameService)]
+    pub replacement_domain: AccountInfo<'info>,
+
+    /// CHECK: PDA owning the loan's domains
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// Required for P2P loans: the current lender, approving the replacement.
+    pub lender: Option<Signer<'info>>,
+
+    /// P2P loans still backed by their offer: the offer.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Name Service Program
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: V1 SOL record of a replacement primary domain; set to `payout` when present
+    #[account(mut)]
+    pub replacement_sol_record: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts[0]: subdomain registrar PDA of the replacement. The
+    // rest, when releasing the primary domain: its snapshotted record accounts in snapshot order, then record
+    // accounts of the replacement to escrow and snapshot (all writable).
+}
+
+/// Releases one escrowed domain of an active loan in exchange for a
+/// replacement that passes the same admission checks. Pool loans lend a flat
+/// amount per admitted domain, so any such replacement is worth as much;
+/// P2P lenders priced the specific domain and must approve the swap.
+/// A replacement primary domain is set up like at `setup_collateral`: its SOL
+/// record points at `payout` and its records are snapshotted. `payout` is
+/// unused when a basket domain is swapped.
+pub fn swap_collateral<'info>(ctx: Context<'_, '_, 'info, 'info, SwapCollateral<'info>>, payout: Pubkey) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let released = ctx.accounts.released_domain.key();
+    let replacement = ctx.accounts.replacement_domain.key();
+
+    admit_domain(&ctx.accounts.replacement_domain, &ctx.accounts.borrower.key())?;
+    let registrar_program = ctx.accounts.global_state.subdomain_registrar_program;
+    check_no_registrar(&replacement, &registrar_program, ctx.remaining_accounts.first())?;
+    if loan.loan_type == LoanType::P2P {
+        let lender = ctx.accounts.lender.as_ref().ok_or(ErrorCode::Unauthorized)?;
+        check_lender_signer(
+            loan,
+            lender.key,
+            ctx.accounts.offer_account.as_ref(),
+            ctx.accounts.claim_account.as_ref(),
+        )?;
+    }
+
+    // The record snapshot and SOL record state describe the primary domain. A
+    // replacement primary is set up while the borrower can still sign for it.
+    let old_snapshot = loan.record_snapshot;
+    let mut old_records: &[AccountInfo<'info>] = &[];
+    if released == loan.domain_registry {
+        let records = ctx.remaining_accounts.get(1..).unwrap_or_default();
+        require!(records.len() >= old_snapshot.records().len(), ErrorCode::RecordSnapshotMismatch);
+        let (old, new) = records.split_at(old_snapshot.records().len());
+        old_records = old;
+
+        loan.record_snapshot = RecordSnapshot::default();
+        escrow_records(
+            loan,
+            &ctx.accounts.name_service_program,
+            &ctx.accounts.replacement_domain,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.escrow_pda.key(),
+            ctx.accounts.replacement_sol_record.as_ref(),
+            &payout,
+            new,
+        )?;
+    }
+
+    let loan_key = loan.key();
+    let seeds = &[
+        b"escrow",
+        loan_key.as_ref(),
+        &[ctx.bumps.escrow_pda]
+    ];
+    let signer = &[&seeds[..]];
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.replacement_domain,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        &[],
+    )?;
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.released_domain,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        signer,
+    )?;
+
+    return_records(
+        &old_snapshot,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        old_records,
+        signer,
+    )?;
+    loan.replace_domain(&released, replacement)?;
+
+    Ok(())
+}
+
+/// Transfers every basket domain from the escrow to `recipient`. `accounts`
+/// must start with the basket domains in basket order; the accounts after
+/// them are returned.
+pub(crate) fn release_basket<'a, 'info>(
+    loan: &LoanAccount,
+    name_service: &AccountInfo<'info>,
+    escrow_pda: &AccountInfo<'info>,
+    recipient: &Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<&'a [AccountInfo<'info>]> {
+    let basket = loan.basket();
+    require!(accounts.len() >= basket.len(), ErrorCode::BasketMismatch);
+    let (domains, rest) = accounts.split_at(basket.len());
+    for (domain, key) in domains.iter().zip(basket) {
+        require_keys_eq!(domain.key(), *key, ErrorCode::BasketMismatch);
+        sns::transfer(name_service, domain, escrow_pda, recipient, signer_seeds)?;
+    }
+    Ok(rest)
+}
@@ -1,19 +1,25 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use crate::state::{LoanAccount, LoanStatus, LoanType, PoolState, OfferAccount};
-use crate::constants::{LOAN_SEED, POOL_SEED, OFFER_SEED, VAULT_SEED};
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{LoanAccount, LoanStatus, LoanType, PoolState, OfferAccount, LenderProfile, GlobalState};
+use crate::constants::{
+    GLOBAL_SEED, LOAN_SEED, OFFER_SEED, VAULT_SEED, LENDER_SEED, REQUEST_SEED, NAME_SERVICE_ID,
+    POOL_LOAN_PRINCIPAL, POOL_LOAN_DURATION, POOL_APR_BPS,
+};
 use crate::error::ErrorCode;
-
-const NAME_SERVICE_ID: Pubkey = pubkey!("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX");
+use crate::sns;
+use crate::admission::{admit_domain, check_escrowed, check_no_registrar};
+use crate::instructions::claim::{claim_holder, check_lender_signer};
+use crate::instructions::record::{write_sol_record, escrow_records, check_snapshot, return_records};
+use crate::instructions::basket::release_basket;
 
 #[derive(Accounts)]
-#[instruction(mode: LoanTypeInput, offer_id: Option<Pubkey>)]
+#[instruction(mode: LoanTypeInput, offer_id: Option<Pubkey>, payout: Pubkey)]
 pub struct SetupCollateral<'info> {
     #[account(mut)]
     pub borrower: Signer<'info>,
 
     /// CHECK: Validated manualy via data inspection
-    #[account(mut)]
+    #[account(mut, owner = NAME_SERVICE_ID @ ErrorCode::DomainNotNameService)]
     pub domain_registry: AccountInfo<'info>,
 
     #[account(
@@ -33,7 +39,16 @@ pub struct SetupCollateral<'info> {
     #[account(address = NAME_SERVICE_ID)]
     pub name_service_program: AccountInfo<'info>,
 
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: V1 SOL record of the domain; set to `payout` when present
+    #[account(mut)]
+    pub sol_record: Option<AccountInfo<'info>>,
+
     pub system_program: Program<'info, System>,
+    // remaining_accounts[0]: subdomain registrar PDA of the domain. The rest:
+    // record accounts of the domain to escrow and snapshot (writable).
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
@@ -42,45 +57,46 @@ pub enum LoanTypeInput {
     P2P,
 }
 
-pub fn setup_collateral(
-    ctx: Context<SetupCollateral>,
+pub fn setup_collateral<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SetupCollateral<'info>>,
     mode: LoanTypeInput,
-    offer_id: Option<Pubkey>
+    offer_id: Option<Pubkey>,
+    payout: Pubkey,
 ) -> Result<()> {
     let loan_account = &mut ctx.accounts.loan_account;
 
-    // Check Owner
-    let data = ctx.accounts.domain_registry.try_borrow_data()?;
-    if data.len() < 96 {
-        return err!(ErrorCode::InvalidDomainOwner);
-    }
-    let owner_slice = &data[32..64];
-    let owner = Pubkey::try_from(owner_slice).unwrap();
-    require_keys_eq!(owner, ctx.accounts.borrower.key(), ErrorCode::InvalidDomainOwner);
+    admit_domain(&ctx.accounts.domain_registry, &ctx.accounts.borrower.key())?;
+    check_no_registrar(
+        &ctx.accounts.domain_registry.key(),
+        &ctx.accounts.global_state.subdomain_registrar_program,
+        ctx.remaining_accounts.first(),
+    )?;
+
+    // Point the SOL record at the payout address while the borrower can still sign for it
+    escrow_records(
+        loan_account,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        ctx.accounts.sol_record.as_ref(),
+        &payout,
+        ctx.remaining_accounts.get(1..).unwrap_or_default(),
+    )?;
 
     // CPI Transfer to Escrow
-    let transfer_instruction_data = vec![1]; // Tag for Transfer
-    let transfer_accounts = vec![
-        AccountMeta::new(ctx.accounts.domain_registry.key(), false),
-        AccountMeta::new(ctx.accounts.escrow_pda.key(), false),
-        AccountMeta::new_readonly(ctx.accounts.borrower.key(), true),
-    ];
-    let ix = anchor_lang::solana_program::instruction::Instruction {
-        program_id: ctx.accounts.name_service_program.key(),
-        accounts: transfer_accounts,
-        data: [transfer_instruction_data, ctx.accounts.escrow_pda.key().to_bytes().to_vec()].concat(),
-    };
-    anchor_lang::solana_program::program::invoke(
-        &ix,
-        &[
-            ctx.accounts.domain_registry.to_account_info(),
-            ctx.accounts.escrow_pda.to_account_info(),
-            ctx.accounts.borrower.to_account_info(),
-        ],
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        &[],
     )?;
 
     loan_account.borrower = ctx.accounts.borrower.key();
     loan_account.domain_registry = ctx.accounts.domain_registry.key();
+    loan_account.seed_domain = ctx.accounts.domain_registry.key();
     loan_account.escrow_pda = ctx.accounts.escrow_pda.key();
     loan_account.status = LoanStatus::SetupPending;
     loan_account.bump = ctx.bumps.loan_account;
@@ -102,6 +118,83 @@ pub fn setup_collateral(
     Ok(())
 }
 
+#[derive(Accounts)]
+pub struct CancelSetup<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    // Only a SetupPending loan can be cancelled: once verify_and_withdraw has
+    // disbursed funds the status is Active and this constraint fails.
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::SetupPending,
+        close = borrower
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: PDA owning domain
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Must be the domain escrowed by this loan
+    #[account(mut, address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+    // remaining_accounts: basket domains in basket order, then snapshotted
+    // record accounts to hand back (all writable).
+}
+
+pub fn cancel_setup<'info>(ctx: Context<'_, '_, 'info, 'info, CancelSetup<'info>>) -> Result<()> {
+    let loan = &ctx.accounts.loan_account;
+    require!(loan.principal_amount == 0, ErrorCode::LoanAlreadyActive);
+
+    // Request loans go through cancel_request, which also closes the request.
+    let (request, _) = Pubkey::find_program_address(&[REQUEST_SEED, loan.key().as_ref()], ctx.program_id);
+    require_keys_neq!(loan.lender_source, request, ErrorCode::LoanHasRequest);
+
+    // Return Domain
+    let loan_key = loan.key();
+    let seeds = &[
+        b"escrow",
+        loan_key.as_ref(),
+        &[ctx.bumps.escrow_pda]
+    ];
+    let signer = &[&seeds[..]];
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        signer,
+    )?;
+    let records = release_basket(
+        loan,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        ctx.remaining_accounts,
+        signer,
+    )?;
+    return_records(
+        &loan.record_snapshot,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        records,
+        signer,
+    )?;
+
+    // LoanAccount is closed by the `close = borrower` constraint, refunding rent.
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct VerifyAndWithdrawPool<'info> {
     #[account(mut)]
@@ -109,7 +202,7 @@ pub struct VerifyAndWithdrawPool<'info> {
 
     #[account(
         mut,
-        seeds = [LOAN_SEED, loan_account.domain_registry.as_ref()],
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
         bump = loan_account.bump,
         constraint = loan_account.borrower == borrower.key(),
         constraint = loan_account.status == LoanStatus::SetupPending,
@@ -117,6 +210,13 @@ pub struct VerifyAndWithdrawPool<'info> {
     )]
     pub loan_account: Account<'info, LoanAccount>,
 
+    /// CHECK: Must be the domain escrowed by this loan; ownership checked in the handler
+    #[account(address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
     #[account(mut)]
     pub pool_state: Account<'info, PoolState>,
 
@@ -140,22 +240,34 @@ pub struct VerifyAndWithdrawPool<'info> {
 }
 
 pub fn verify_and_withdraw_pool(ctx: Context<VerifyAndWithdrawPool>) -> Result<()> {
+    check_escrowed(&ctx.accounts.domain_registry, &ctx.accounts.loan_account.escrow_pda)?;
+
     let loan = &mut ctx.accounts.loan_account;
     let pool = &mut ctx.accounts.pool_state;
     let now = ctx.accounts.clock.unix_timestamp;
 
-    // MVP Policy: Fixed small loan for Pool
-    let principal = 10_000_000; // 10 USDC
-    let duration = 86400 * 14; // 14 days
-    let apr = 1000; // 10%
+    // MVP Policy: Fixed small loan per escrowed domain for Pool. swap_collateral
+    // relies on this flat pricing to let any admitted domain replace another.
+    let principal = POOL_LOAN_PRINCIPAL
+        .checked_mul(loan.domain_count() as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let duration = POOL_LOAN_DURATION;
+    let apr = POOL_APR_BPS;
 
     // Update Loan
     loan.principal_amount = principal;
     loan.apr_bps = apr;
     loan.start_ts = now;
     loan.due_ts = now + duration;
+    loan.duration_seconds = duration;
+    loan.set_min_interest(
+        ctx.accounts.global_state.pool_min_interest_seconds,
+        ctx.accounts.global_state.pool_origination_interest_bps,
+    )?;
     loan.status = LoanStatus::Active;
     loan.lender_source = pool.key();
+    loan.mint = ctx.accounts.pool_vault.mint;
+    pool.record_disbursement(principal)?;
 
     // Transfer Funds
     let pool_key = pool.key();
@@ -184,7 +296,7 @@ pub struct VerifyAndWithdrawP2P<'info> {
 
     #[account(
         mut,
-        seeds = [LOAN_SEED, loan_account.domain_registry.as_ref()],
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
         bump = loan_account.bump,
         constraint = loan_account.borrower == borrower.key(),
         constraint = loan_account.status == LoanStatus::SetupPending,
@@ -193,6 +305,10 @@ pub struct VerifyAndWithdrawP2P<'info> {
     )]
     pub loan_account: Account<'info, LoanAccount>,
 
+    /// CHECK: Must be the domain escrowed by this loan; ownership checked in the handler
+    #[account(address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
     #[account(mut)]
     pub offer_account: Account<'info, OfferAccount>,
 
@@ -204,6 +320,10 @@ pub struct VerifyAndWithdrawP2P<'info> {
     )]
     pub offer_vault: Account<'info, TokenAccount>,
 
+    /// CHECK: Lender profile PDA; may not exist yet
+    #[account(seeds = [LENDER_SEED, offer_account.lender.as_ref()], bump)]
+    pub lender_profile: AccountInfo<'info>,
+
     #[account(mut)]
     pub borrower_usdc: Account<'info, TokenAccount>,
 
@@ -211,16 +331,15 @@ pub struct VerifyAndWithdrawP2P<'info> {
     pub clock: Sysvar<'info, Clock>,
 }
 
-pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>) -> Result<()> {
+pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>, amount: u64) -> Result<()> {
+    check_escrowed(&ctx.accounts.domain_registry, &ctx.accounts.loan_account.escrow_pda)?;
+
     let now = ctx.accounts.clock.unix_timestamp;
 
     // We access fields before borrowing offer_account mutably via ctx.accounts if possible,
     // but here we need mutable access for update.
     // To avoid immutable borrow error later when getting account_info, we extract needed values first.
 
-    let principal = ctx.accounts.offer_account.principal;
-    let apr_bps = ctx.accounts.offer_account.apr_bps;
-    let duration = ctx.accounts.offer_account.duration_seconds;
     let expiry = ctx.accounts.offer_account.offer_expiry;
     let is_active = ctx.accounts.offer_account.is_active;
     let lender = ctx.accounts.offer_account.lender;
@@ -229,18 +348,22 @@ pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>) -> Result<()>
 
     require!(is_active, ErrorCode::OfferExpired);
     require!(now < expiry, ErrorCode::OfferExpired);
+    require!(
+        nonce >= LenderProfile::min_nonce_of(&ctx.accounts.lender_profile)?,
+        ErrorCode::NonceInvalidated
+    );
 
     // Update Loan
     let loan = &mut ctx.accounts.loan_account;
-    loan.principal_amount = principal;
-    loan.apr_bps = apr_bps;
-    loan.start_ts = now;
-    loan.due_ts = now + duration;
+    loan.principal_amount = amount;
+    loan.apply_offer_terms(&ctx.accounts.offer_account, now)?;
     loan.status = LoanStatus::Active;
+    loan.mint = ctx.accounts.offer_vault.mint;
 
-    // Close Offer (Mark taken)
+    // Draw against the offer; it stays open for other borrowers until its
+    // remaining capacity is exhausted.
     let offer_account = &mut ctx.accounts.offer_account;
-    offer_account.is_active = false;
+    offer_account.draw(amount)?;
 
     // Transfer Funds
     // We need the OfferAccount PDA to sign.
@@ -261,7 +384,7 @@ pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>) -> Result<()>
         authority: offer_account.to_account_info(),
     };
     let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
-    token::transfer(cpi_ctx, principal)?;
+    token::transfer(cpi_ctx, amount)?;
 
     Ok(())
 }
@@ -273,39 +396,70 @@ pub struct Repay<'info> {
 
     #[account(
         mut,
-        seeds = [LOAN_SEED, loan_account.domain_registry.as_ref()],
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
         bump = loan_account.bump,
         constraint = loan_account.borrower == borrower.key(),
         constraint = loan_account.status == LoanStatus::Active
     )]
     pub loan_account: Account<'info, LoanAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = borrower_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch)]
     pub borrower_usdc: Account<'info, TokenAccount>,
 
     #[account(mut)]
     pub destination_vault: Account<'info, TokenAccount>, // Pool or Lender
 
+    /// Required for P2P loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required for Pool loans: the pool that funded the loan.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
     /// CHECK: PDA owning domain
     #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
     pub escrow_pda: AccountInfo<'info>,
 
     /// CHECK: Original domain registry
-    #[account(mut)]
+    #[account(mut, address = loan_account.domain_registry)]
     pub domain_registry: AccountInfo<'info>,
 
+    /// CHECK: V1 SOL record of the domain; restored to `record_payout` when present
+    #[account(mut)]
+    pub sol_record: Option<AccountInfo<'info>>,
+
     /// CHECK: Name Service
     #[account(address = NAME_SERVICE_ID)]
     pub name_service_program: AccountInfo<'info>,
 
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+    // remaining_accounts: basket domains in basket order, then the
+    // snapshotted record accounts in snapshot order (all writable).
 }
 
-pub fn repay(ctx: Context<Repay>) -> Result<()> {
+/// Repays the loan in full and returns the domain. The borrower pays
+/// `LoanAccount::amount_due`: principal plus accrued interest, never less
+/// than the remaining minimum interest.
+pub fn repay<'info>(ctx: Context<'_, '_, 'info, 'info, Repay<'info>>) -> Result<()> {
     let loan = &mut ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
 
     // Calculate Repayment
-    let amount_due = loan.principal_amount;
+    let amount_due = book_payoff(
+        loan,
+        &ctx.accounts.destination_vault,
+        ctx.accounts.offer_account.as_mut(),
+        ctx.accounts.pool_state.as_mut(),
+        ctx.accounts.claim_account.as_ref(),
+        now,
+        ctx.program_id,
+    )?;
 
     // Transfer USDC
     let cpi_accounts = Transfer {
@@ -325,30 +479,706 @@ pub fn repay(ctx: Context<Repay>) -> Result<()> {
     ];
     let signer = &[&seeds[..]];
 
-    let transfer_instruction_data = vec![1]; // Tag for Transfer
-    let transfer_accounts = vec![
-        AccountMeta::new(ctx.accounts.domain_registry.key(), false),
-        AccountMeta::new(ctx.accounts.borrower.key(), false), // New owner = borrower
-        AccountMeta::new_readonly(ctx.accounts.escrow_pda.key(), true), // Signer
+    let records = release_basket(
+        loan,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        ctx.remaining_accounts,
+        signer,
+    )?;
+    check_snapshot(&loan.record_snapshot, &ctx.accounts.domain_registry, records)?;
+
+    if let Some(sol_record) = ctx.accounts.sol_record.as_ref() {
+        restore_sol_record(
+            loan,
+            &ctx.accounts.name_service_program,
+            sol_record,
+            &ctx.accounts.escrow_pda,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.domain_registry,
+            &ctx.accounts.borrower.key(),
+            signer,
+        )?;
+    }
+    return_records(
+        &loan.record_snapshot,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        records,
+        signer,
+    )?;
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        signer,
+    )?;
+
+    mark_repaid(loan, amount_due, now)
+}
+
+#[derive(Accounts)]
+pub struct RepayFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.status == LoanStatus::Active
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(mut, constraint = payer_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch)]
+    pub payer_usdc: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination_vault: Account<'info, TokenAccount>, // Pool or Lender
+
+    /// Required for P2P loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required for Pool loans: the pool that funded the loan.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
+    /// CHECK: New domain owner; the borrower unless the payer is the borrower's repay delegate
+    #[account(mut)]
+    pub domain_recipient: AccountInfo<'info>,
+
+    /// CHECK: PDA owning domain
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Must be the domain escrowed by this loan
+    #[account(mut, address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: V1 SOL record of the domain; restored to `record_payout` when
+    /// present and the domain goes back to the borrower
+    #[account(mut)]
+    pub sol_record: Option<AccountInfo<'info>>,
+
+    /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+    // remaining_accounts: basket domains in basket order, then the
+    // snapshotted record accounts in snapshot order (all writable).
+}
+
+/// Repays a loan on the borrower's behalf. The domain goes back to the
+/// borrower, unless the payer is the borrower's pre-authorized repay
+/// delegate, who may pick a different recipient.
+pub fn repay_for<'info>(ctx: Context<'_, '_, 'info, 'info, RepayFor<'info>>) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    let recipient = ctx.accounts.domain_recipient.key();
+    if recipient != loan.borrower {
+        require!(
+            loan.repay_delegate != Pubkey::default() && loan.repay_delegate == ctx.accounts.payer.key(),
+            ErrorCode::Unauthorized
+        );
+    }
+
+    // Calculate Repayment
+    let amount_due = book_payoff(
+        loan,
+        &ctx.accounts.destination_vault,
+        ctx.accounts.offer_account.as_mut(),
+        ctx.accounts.pool_state.as_mut(),
+        ctx.accounts.claim_account.as_ref(),
+        now,
+        ctx.program_id,
+    )?;
+
+    // Transfer USDC from the payer
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.payer_usdc.to_account_info(),
+        to: ctx.accounts.destination_vault.to_account_info(),
+        authority: ctx.accounts.payer.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, amount_due)?;
+
+    // Return Domain
+    let loan_key = loan.key();
+    let seeds = &[
+        b"escrow",
+        loan_key.as_ref(),
+        &[ctx.bumps.escrow_pda]
     ];
-    let ix = anchor_lang::solana_program::instruction::Instruction {
-        program_id: ctx.accounts.name_service_program.key(),
-        accounts: transfer_accounts,
-        data: [transfer_instruction_data, ctx.accounts.borrower.key().to_bytes().to_vec()].concat(),
+    let signer = &[&seeds[..]];
+
+    let records = release_basket(
+        loan,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &recipient,
+        ctx.remaining_accounts,
+        signer,
+    )?;
+    check_snapshot(&loan.record_snapshot, &ctx.accounts.domain_registry, records)?;
+
+    if let (Some(sol_record), true) = (ctx.accounts.sol_record.as_ref(), recipient == loan.borrower) {
+        restore_sol_record(
+            loan,
+            &ctx.accounts.name_service_program,
+            sol_record,
+            &ctx.accounts.escrow_pda,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.domain_registry,
+            &recipient,
+            signer,
+        )?;
+    }
+    return_records(
+        &loan.record_snapshot,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &recipient,
+        records,
+        signer,
+    )?;
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.escrow_pda,
+        &recipient,
+        signer,
+    )?;
+
+    mark_repaid(loan, amount_due, now)
+}
+
+#[derive(Accounts)]
+pub struct SetRepayDelegate<'info> {
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key()
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+}
+
+/// Authorizes (or with `None`, revokes) a delegate who may repay the loan
+/// via `repay_for` and choose where the domain goes.
+pub fn set_repay_delegate(ctx: Context<SetRepayDelegate>, delegate: Option<Pubkey>) -> Result<()> {
+    ctx.accounts.loan_account.repay_delegate = delegate.unwrap_or_default();
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RepayPartial<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::Active
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(mut, constraint = borrower_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch)]
+    pub borrower_usdc: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination_vault: Account<'info, TokenAccount>, // Pool or Lender
+
+    /// Required for offer-backed P2P loans: the offer backing the loan.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required for Pool loans: the pool that funded the loan.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Pays down part of the balance, first against accrued interest and then
+/// against principal. The domain stays escrowed; the final payment goes
+/// through `repay`, which releases it.
+pub fn repay_partial(ctx: Context<RepayPartial>, amount: u64) -> Result<()> {
+    let now = ctx.accounts.clock.unix_timestamp;
+    let accounts = ctx.accounts;
+    apply_partial_payment(
+        &mut accounts.loan_account,
+        &accounts.borrower,
+        &accounts.borrower_usdc,
+        &accounts.destination_vault,
+        accounts.offer_account.as_ref(),
+        accounts.pool_state.as_mut(),
+        accounts.claim_account.as_ref(),
+        &accounts.token_program,
+        amount,
+        now,
+        ctx.program_id,
+    )
+}
+
+/// Pays the next scheduled installment of an installment loan. It takes the
+/// accounts of `repay`: once an installment covers the remaining balance,
+/// the loan is repaid in full instead and the domain released.
+pub fn pay_installment<'info>(ctx: Context<'_, '_, 'info, 'info, Repay<'info>>) -> Result<()> {
+    let loan = &ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+    require!(loan.installment_count > 0, ErrorCode::NoInstallmentSchedule);
+    let amount = loan.installment_amount;
+    if amount >= loan.amount_due(now)? {
+        return repay(ctx);
+    }
+
+    let accounts = ctx.accounts;
+    apply_partial_payment(
+        &mut accounts.loan_account,
+        &accounts.borrower,
+        &accounts.borrower_usdc,
+        &accounts.destination_vault,
+        accounts.offer_account.as_ref(),
+        accounts.pool_state.as_mut(),
+        accounts.claim_account.as_ref(),
+        &accounts.token_program,
+        amount,
+        now,
+        ctx.program_id,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_partial_payment<'info>(
+    loan: &mut LoanAccount,
+    borrower: &Signer<'info>,
+    borrower_usdc: &Account<'info, TokenAccount>,
+    destination: &Account<'info, TokenAccount>,
+    offer: Option<&Account<'info, OfferAccount>>,
+    pool: Option<&mut Account<'info, PoolState>>,
+    claim: Option<&Account<'info, TokenAccount>>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+    now: i64,
+    program_id: &Pubkey,
+) -> Result<()> {
+    check_creditor_destination(loan, destination, offer, claim, program_id)?;
+
+    let (to_principal, to_interest) = loan.apply_partial_payment(amount, now)?;
+    credit_pool(loan, pool, to_principal, to_interest)?;
+
+    // Transfer USDC
+    let cpi_accounts = Transfer {
+        from: borrower_usdc.to_account_info(),
+        to: destination.to_account_info(),
+        authority: borrower.to_account_info(),
     };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, amount)
+}
+
+#[derive(Accounts)]
+pub struct Refinance<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::Active,
+        constraint = loan_account.lender_source != new_offer.key()
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(mut)]
+    pub new_offer: Account<'info, OfferAccount>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, new_offer.key().as_ref()],
+        bump,
+        token::authority = new_offer
+    )]
+    pub new_offer_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Lender profile PDA; may not exist yet
+    #[account(seeds = [LENDER_SEED, new_offer.lender.as_ref()], bump)]
+    pub lender_profile: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = old_creditor_vault.mint == new_offer_vault.mint @ ErrorCode::MintMismatch
+    )]
+    pub old_creditor_vault: Account<'info, TokenAccount>, // Pool or Lender
+
+    /// Required when the loan is currently backed by an offer.
+    #[account(mut)]
+    pub old_offer: Option<Account<'info, OfferAccount>>,
+
+    /// Required when the loan is currently funded by the pool.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
+    #[account(mut, constraint = borrower_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch)]
+    pub borrower_usdc: Account<'info, TokenAccount>,
 
-    anchor_lang::solana_program::program::invoke_signed(
-        &ix,
-        &[
-            ctx.accounts.domain_registry.to_account_info(),
-            ctx.accounts.borrower.to_account_info(),
-            ctx.accounts.escrow_pda.to_account_info(),
-        ],
-        signer
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Moves an active loan onto `new_offer` without releasing the domain. The
+/// new offer draws `amount`, pays off the current creditor (principal plus
+/// accrued interest), and any excess goes to the borrower; a shortfall is
+/// paid by the borrower.
+pub fn refinance(ctx: Context<Refinance>, amount: u64) -> Result<()> {
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    let offer = &ctx.accounts.new_offer;
+    require!(offer.is_active, ErrorCode::OfferExpired);
+    require!(now < offer.offer_expiry, ErrorCode::OfferExpired);
+    require!(
+        offer.nonce >= LenderProfile::min_nonce_of(&ctx.accounts.lender_profile)?,
+        ErrorCode::NonceInvalidated
+    );
+
+    let loan = &mut ctx.accounts.loan_account;
+    let interest = loan.payoff_interest(now)?;
+    let payoff = loan.amount_due(now)?;
+
+    release_creditor(
+        loan,
+        &ctx.accounts.old_creditor_vault,
+        ctx.accounts.old_offer.as_mut(),
+        ctx.accounts.claim_account.as_ref(),
+        ctx.program_id,
     )?;
+    credit_pool(loan, ctx.accounts.pool_state.as_mut(), loan.principal_amount, interest)?;
 
-    loan.status = LoanStatus::Repaid;
-    loan.repaid_amount = amount_due;
+    let new_offer = &mut ctx.accounts.new_offer;
+    new_offer.draw(amount)?;
 
+    // Replace terms; the domain stays in the same escrow PDA.
+    loan.principal_amount = amount;
+    loan.unpaid_interest = 0;
+    loan.repaid_amount = loan.repaid_amount.checked_add(payoff).ok_or(ErrorCode::MathOverflow)?;
+    loan.apply_offer_terms(new_offer, now)?;
+    loan.loan_type = LoanType::P2P;
+    loan.lender_source = new_offer.key();
+    loan.creditor = Pubkey::default();
+    // The paid-off claim token is retired; mint_loan_claim issues a new one.
+    loan.claim_mint = Pubkey::default();
+
+    let nonce_bytes = new_offer.nonce.to_le_bytes();
+    let seeds = &[
+        OFFER_SEED,
+        new_offer.lender.as_ref(),
+        nonce_bytes.as_ref(),
+        &[new_offer.bump]
+    ];
+    let signer = &[&seeds[..]];
+
+    // New offer -> old creditor, up to the payoff
+    let from_offer = amount.min(payoff);
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.new_offer_vault.to_account_info(),
+        to: ctx.accounts.old_creditor_vault.to_account_info(),
+        authority: new_offer.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, from_offer)?;
+
+    if amount > payoff {
+        // Excess principal goes to the borrower
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.new_offer_vault.to_account_info(),
+            to: ctx.accounts.borrower_usdc.to_account_info(),
+            authority: new_offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount - payoff)?;
+    } else if payoff > amount {
+        // Borrower covers the shortfall
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.borrower_usdc.to_account_info(),
+            to: ctx.accounts.old_creditor_vault.to_account_info(),
+            authority: ctx.accounts.borrower.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, payoff - amount)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Rollover<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::Active
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut, constraint = borrower_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch)]
+    pub borrower_usdc: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination_vault: Account<'info, TokenAccount>, // Pool or Lender
+
+    /// Required for offer-backed P2P loans: the offer backing the loan.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required for Pool loans: the pool that funded the loan.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
+    /// P2P lender approving a rollover beyond the pre-agreed count.
+    pub lender: Option<Signer<'info>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Extends a loan by one tenor in exchange for the interest accrued so far.
+/// Pool loans may roll up to `GlobalState::max_pool_rollovers` times; P2P
+/// loans up to the offer's `max_rollovers`, or further with the lender's
+/// signature.
+pub fn rollover(ctx: Context<Rollover>) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    check_creditor_destination(
+        loan,
+        &ctx.accounts.destination_vault,
+        ctx.accounts.offer_account.as_ref(),
+        ctx.accounts.claim_account.as_ref(),
+        ctx.program_id,
+    )?;
+
+    // Installment loans follow their schedule and callable loans have no
+    // maturity to extend.
+    require!(loan.installment_count == 0 && !loan.callable, ErrorCode::RolloverNotAllowed);
+
+    match loan.loan_type {
+        LoanType::Pool => {
+            require!(
+                loan.rollover_count < ctx.accounts.global_state.max_pool_rollovers,
+                ErrorCode::RolloverNotAllowed
+            );
+        }
+        LoanType::P2P => {
+            let lender_signed = match &ctx.accounts.lender {
+                Some(lender) => {
+                    check_lender_signer(
+                        loan,
+                        &lender.key(),
+                        ctx.accounts.offer_account.as_ref(),
+                        ctx.accounts.claim_account.as_ref(),
+                    )?;
+                    true
+                }
+                None => false,
+            };
+            require!(
+                lender_signed || loan.rollover_count < loan.max_rollovers,
+                ErrorCode::RolloverNotAllowed
+            );
+        }
+    }
+
+    let interest = loan.accrued_interest(now)?;
+    credit_pool(loan, ctx.accounts.pool_state.as_mut(), 0, interest)?;
+
+    // Transfer USDC
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.borrower_usdc.to_account_info(),
+        to: ctx.accounts.destination_vault.to_account_info(),
+        authority: ctx.accounts.borrower.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, interest)?;
+
+    loan.unpaid_interest = 0;
+    loan.apply_interest_paid(interest);
+    loan.repaid_amount = loan.repaid_amount.checked_add(interest).ok_or(ErrorCode::MathOverflow)?;
+    loan.rollover_count = loan.rollover_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    loan.start_ts = now;
+    loan.last_update_ts = now;
+    loan.due_ts = loan.due_ts.checked_add(loan.duration_seconds).ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(())
+}
+
+/// Checks that `destination` holds the loan's mint and belongs to the loan's
+/// current creditor:
+/// - Claim minted: a token account owned by the claim holder
+/// - Pool: the pool vault
+/// - P2P with a direct creditor: a token account owned by that creditor
+/// - P2P from an offer: the offer vault
+pub(crate) fn check_creditor_destination(
+    loan: &LoanAccount,
+    destination: &Account<TokenAccount>,
+    offer: Option<&Account<OfferAccount>>,
+    claim: Option<&Account<TokenAccount>>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(destination.mint, loan.mint, ErrorCode::MintMismatch);
+    if let Some(holder) = claim_holder(loan, claim)? {
+        require_keys_eq!(destination.owner, holder, ErrorCode::CreditorMismatch);
+        return Ok(());
+    }
+    match loan.loan_type {
+        LoanType::Pool => {
+            let (pool_vault, _) = Pubkey::find_program_address(
+                &[VAULT_SEED, loan.lender_source.as_ref(), b"token"],
+                program_id,
+            );
+            require_keys_eq!(destination.key(), pool_vault, ErrorCode::InvalidPayoffDestination);
+        }
+        LoanType::P2P if loan.creditor != Pubkey::default() => {
+            require_keys_eq!(destination.owner, loan.creditor, ErrorCode::CreditorMismatch);
+        }
+        LoanType::P2P => {
+            let offer = offer.ok_or(ErrorCode::OfferMismatch)?;
+            require_keys_eq!(offer.key(), loan.lender_source, ErrorCode::OfferMismatch);
+            let (offer_vault, _) = Pubkey::find_program_address(
+                &[VAULT_SEED, offer.key().as_ref()],
+                program_id,
+            );
+            require_keys_eq!(destination.key(), offer_vault, ErrorCode::InvalidPayoffDestination);
+        }
+    }
+    Ok(())
+}
+
+/// Like `check_creditor_destination`, and additionally releases the loan
+/// from its backing offer when it is paid off. Offer-backed loans must pass
+/// the offer even when a claim holder is paid, so it does not keep counting
+/// the loan as active.
+pub(crate) fn release_creditor(
+    loan: &LoanAccount,
+    destination: &Account<TokenAccount>,
+    offer: Option<&mut Account<OfferAccount>>,
+    claim: Option<&Account<TokenAccount>>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    check_creditor_destination(loan, destination, offer.as_deref(), claim, program_id)?;
+    if loan.loan_type == LoanType::P2P && loan.creditor == Pubkey::default() {
+        let offer = offer.ok_or(ErrorCode::OfferMismatch)?;
+        require_keys_eq!(offer.key(), loan.lender_source, ErrorCode::OfferMismatch);
+        offer.release_loan()?;
+    }
+    Ok(())
+}
+
+/// Books a payment against the funding pool for Pool loans; a no-op for P2P
+/// loans, whose lenders are paid straight into their vault or wallet.
+pub(crate) fn credit_pool(
+    loan: &LoanAccount,
+    pool: Option<&mut Account<PoolState>>,
+    principal: u64,
+    interest: u64,
+) -> Result<()> {
+    if loan.loan_type != LoanType::Pool {
+        return Ok(());
+    }
+    let pool = pool.ok_or(ErrorCode::InvalidPayoffDestination)?;
+    require_keys_eq!(pool.key(), loan.lender_source, ErrorCode::InvalidPayoffDestination);
+    pool.record_repayment(principal, interest)
+}
+
+/// Validates the creditor destination, books the full payoff against the
+/// pool or offer, and returns the amount due at `now`.
+fn book_payoff(
+    loan: &LoanAccount,
+    destination: &Account<TokenAccount>,
+    offer: Option<&mut Account<OfferAccount>>,
+    pool: Option<&mut Account<PoolState>>,
+    claim: Option<&Account<TokenAccount>>,
+    now: i64,
+    program_id: &Pubkey,
+) -> Result<u64> {
+    release_creditor(loan, destination, offer, claim, program_id)?;
+    let interest = loan.payoff_interest(now)?;
+    credit_pool(loan, pool, loan.principal_amount, interest)?;
+    loan.amount_due(now)
+}
+
+/// Points the SOL record back at `record_payout` before the escrow returns
+/// the domain, handing the record to `recipient` if the escrow had to take
+/// it over. No-op when the loan has no payout address.
+#[allow(clippy::too_many_arguments)]
+fn restore_sol_record<'info>(
+    loan: &LoanAccount,
+    name_service: &AccountInfo<'info>,
+    sol_record: &AccountInfo<'info>,
+    escrow_pda: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    domain_registry: &AccountInfo<'info>,
+    recipient: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if loan.record_payout == Pubkey::default() {
+        return Ok(());
+    }
+    let written = write_sol_record(
+        name_service,
+        sol_record,
+        escrow_pda,
+        system_program,
+        domain_registry,
+        &loan.record_payout,
+        signer_seeds,
+    )?;
+    if written && sns::NameRecordHeader::load(sol_record)?.owner == escrow_pda.key() {
+        sns::transfer(name_service, sol_record, escrow_pda, recipient, signer_seeds)?;
+    }
+    Ok(())
+}
+
+/// Closes out a loan after `amount` settled the full balance.
+fn mark_repaid(loan: &mut LoanAccount, amount: u64, now: i64) -> Result<()> {
+    loan.status = LoanStatus::Repaid;
+    loan.repaid_amount = loan.repaid_amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+    loan.principal_amount = 0;
+    loan.unpaid_interest = 0;
+    loan.min_interest = 0;
+    loan.last_update_ts = now;
     Ok(())
 }
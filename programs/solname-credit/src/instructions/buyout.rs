@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{LoanAccount, LoanStatus, LoanType, PoolState, OfferAccount, GlobalState};
+use crate::constants::{GLOBAL_SEED, VAULT_SEED, POOL_APR_BPS, POOL_LOAN_DURATION};
+use crate::error::ErrorCode;
+use crate::instructions::borrow::release_creditor;
+
+#[derive(Accounts)]
+pub struct PoolBuyout<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = loan_account.status == LoanStatus::Active,
+        constraint = loan_account.loan_type == LoanType::P2P
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Validated seeds
+    #[account(seeds = [VAULT_SEED, pool_state.key().as_ref()], bump)]
+    pub vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, pool_state.key().as_ref(), b"token"],
+        bump,
+        token::authority = vault_authority,
+        constraint = pool_vault.mint == loan_account.mint @ ErrorCode::MintMismatch
+    )]
+    pub pool_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub creditor_destination: Account<'info, TokenAccount>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Permissionless: the pool buys a healthy P2P loan from its creditor at par
+/// plus accrued interest and takes it over as a pool loan.
+pub fn pool_buyout(ctx: Context<PoolBuyout>) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    // Pool eligibility
+    let price = loan.amount_due(now)?;
+    require!(price <= ctx.accounts.global_state.pool_buyout_max_amount, ErrorCode::BuyoutNotEligible);
+    require!(loan.apr_bps >= POOL_APR_BPS, ErrorCode::BuyoutNotEligible);
+    require!(!loan.callable && loan.installment_count == 0, ErrorCode::BuyoutNotEligible);
+    let remaining = loan.due_ts.saturating_sub(now);
+    require!(remaining > 0 && remaining <= POOL_LOAN_DURATION, ErrorCode::BuyoutNotEligible);
+    require!(ctx.accounts.pool_vault.amount >= price, ErrorCode::InsufficientLiquidity);
+
+    release_creditor(
+        loan,
+        &ctx.accounts.creditor_destination,
+        ctx.accounts.offer_account.as_mut(),
+        ctx.accounts.claim_account.as_ref(),
+        ctx.program_id,
+    )?;
+
+    let pool = &mut ctx.accounts.pool_state;
+    pool.record_disbursement(price)?;
+
+    // The accrued interest is capitalized into the pool's principal.
+    loan.principal_amount = price;
+    loan.unpaid_interest = 0;
+    loan.min_interest = 0;
+    loan.start_ts = now;
+    loan.last_update_ts = now;
+    loan.rollover_count = 0;
+    loan.loan_type = LoanType::Pool;
+    loan.lender_source = pool.key();
+    loan.creditor = Pubkey::default();
+    loan.claim_mint = Pubkey::default();
+
+    let pool_key = pool.key();
+    let seeds = &[
+        VAULT_SEED,
+        pool_key.as_ref(),
+        &[ctx.bumps.vault_authority]
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.pool_vault.to_account_info(),
+        to: ctx.accounts.creditor_destination.to_account_info(),
+        authority: ctx.accounts.vault_authority.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, price)?;
+
+    Ok(())
+}
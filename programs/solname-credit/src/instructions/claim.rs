@@ -0,0 +1,347 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Approve, Mint, MintTo, SetAuthority, Token, TokenAccount, Transfer};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use crate::state::{LoanAccount, LoanStatus, LoanType, OfferAccount, ClaimListing};
+use crate::constants::{LOAN_SEED, CLAIM_SEED, LISTING_SEED};
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct MintLoanClaim<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.status == LoanStatus::Active,
+        constraint = loan_account.loan_type == LoanType::P2P,
+        constraint = loan_account.claim_mint == Pubkey::default() @ ErrorCode::InvalidLoanClaim
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    #[account(
+        init,
+        seeds = [CLAIM_SEED, loan_account.key().as_ref(), &[loan_account.claim_generation]],
+        bump,
+        payer = lender,
+        mint::decimals = 0,
+        mint::authority = loan_account
+    )]
+    pub claim_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = claim_mint,
+        associated_token::authority = lender
+    )]
+    pub lender_claim_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Mints the one-of-one claim token for a P2P loan to its lender. From then
+/// on the loan is paid to, and lender-side actions are taken by, whoever
+/// holds the token. An offer-backed loan is released from its offer.
+pub fn mint_loan_claim(ctx: Context<MintLoanClaim>) -> Result<()> {
+    let lender = ctx.accounts.lender.key();
+    check_lender_signer(
+        &ctx.accounts.loan_account,
+        &lender,
+        ctx.accounts.offer_account.as_ref(),
+        None,
+    )?;
+
+    let loan = &mut ctx.accounts.loan_account;
+    if loan.creditor == Pubkey::default() {
+        // `check_lender_signer` required the backing offer.
+        if let Some(offer) = ctx.accounts.offer_account.as_mut() {
+            offer.release_loan()?;
+        }
+        loan.creditor = lender;
+    }
+    loan.claim_mint = ctx.accounts.claim_mint.key();
+    loan.claim_generation = loan.claim_generation.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+    let seed_domain = loan.seed_domain;
+    let seeds = &[LOAN_SEED, seed_domain.as_ref(), &[loan.bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.claim_mint.to_account_info(),
+        to: ctx.accounts.lender_claim_account.to_account_info(),
+        authority: loan.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::mint_to(cpi_ctx, 1)?;
+
+    // Fix the supply at one.
+    let cpi_accounts = SetAuthority {
+        current_authority: loan.to_account_info(),
+        account_or_mint: ctx.accounts.claim_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ListLoanClaim<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        constraint = loan_account.status == LoanStatus::Active || loan_account.status == LoanStatus::Grace,
+        constraint = loan_account.loan_type == LoanType::P2P
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(
+        init,
+        seeds = [LISTING_SEED, loan_account.key().as_ref()],
+        bump,
+        payer = seller,
+        space = 8 + std::mem::size_of::<ClaimListing>()
+    )]
+    pub listing: Account<'info, ClaimListing>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the seller's token account holding it.
+    #[account(mut)]
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Offers the loan's creditor rights for `price`. If a claim token exists,
+/// the listing is approved as its delegate so a buyer can take it without
+/// the seller.
+pub fn list_loan_claim(ctx: Context<ListLoanClaim>, price: u64) -> Result<()> {
+    require!(price > 0, ErrorCode::InvalidAmount);
+    check_lender_signer(
+        &ctx.accounts.loan_account,
+        &ctx.accounts.seller.key(),
+        ctx.accounts.offer_account.as_ref(),
+        ctx.accounts.claim_account.as_ref(),
+    )?;
+
+    if let Some(claim) = ctx.accounts.claim_account.as_ref() {
+        let cpi_accounts = Approve {
+            to: claim.to_account_info(),
+            delegate: ctx.accounts.listing.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::approve(cpi_ctx, 1)?;
+    }
+
+    let listing = &mut ctx.accounts.listing;
+    listing.loan = ctx.accounts.loan_account.key();
+    listing.seller = ctx.accounts.seller.key();
+    listing.price = price;
+    listing.bump = ctx.bumps.listing;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct BuyLoanClaim<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LISTING_SEED, loan_account.key().as_ref()],
+        bump = listing.bump,
+        has_one = seller,
+        close = seller
+    )]
+    pub listing: Account<'info, ClaimListing>,
+
+    // A listing can no longer be filled once the loan is repaid or in auction.
+    #[account(
+        mut,
+        constraint = loan_account.status == LoanStatus::Active || loan_account.status == LoanStatus::Grace
+            @ ErrorCode::ListingInvalid
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: Listing seller; receives the listing rent
+    #[account(mut)]
+    pub seller: AccountInfo<'info>,
+
+    // Claims are priced in the loan's own mint.
+    #[account(
+        mut,
+        constraint = seller_usdc.owner == seller.key(),
+        constraint = seller_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch
+    )]
+    pub seller_usdc: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub buyer_usdc: Account<'info, TokenAccount>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the seller's token account holding it.
+    #[account(mut)]
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    /// Required once a claim token was minted: receives it.
+    #[account(
+        mut,
+        constraint = buyer_claim_account.owner == buyer.key(),
+        constraint = buyer_claim_account.mint == loan_account.claim_mint
+    )]
+    pub buyer_claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Buys a listed loan claim: the buyer pays the seller and becomes the
+/// loan's creditor in the same instruction.
+pub fn buy_loan_claim(ctx: Context<BuyLoanClaim>) -> Result<()> {
+    let seller = ctx.accounts.listing.seller;
+    let lender = current_lender(
+        &ctx.accounts.loan_account,
+        ctx.accounts.offer_account.as_ref(),
+        ctx.accounts.claim_account.as_ref(),
+    )?;
+    require_keys_eq!(lender, seller, ErrorCode::ListingInvalid);
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.buyer_usdc.to_account_info(),
+        to: ctx.accounts.seller_usdc.to_account_info(),
+        authority: ctx.accounts.buyer.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, ctx.accounts.listing.price)?;
+
+    let loan = &mut ctx.accounts.loan_account;
+    let buyer = ctx.accounts.buyer.key();
+    if loan.claim_mint != Pubkey::default() {
+        let claim = ctx.accounts.claim_account.as_ref().ok_or(ErrorCode::InvalidLoanClaim)?;
+        let buyer_claim = ctx.accounts.buyer_claim_account.as_ref().ok_or(ErrorCode::InvalidLoanClaim)?;
+
+        let loan_key = loan.key();
+        let seeds = &[LISTING_SEED, loan_key.as_ref(), &[ctx.accounts.listing.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: claim.to_account_info(),
+            to: buyer_claim.to_account_info(),
+            authority: ctx.accounts.listing.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_ctx, 1)?;
+    } else {
+        if loan.creditor == Pubkey::default() {
+            // `current_lender` required the backing offer.
+            if let Some(offer) = ctx.accounts.offer_account.as_mut() {
+                offer.release_loan()?;
+            }
+        }
+        loan.creditor = buyer;
+        loan.lender_source = buyer;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct DelistLoanClaim<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LISTING_SEED, loan_account.key().as_ref()],
+        bump = listing.bump,
+        has_one = seller,
+        close = seller
+    )]
+    pub listing: Account<'info, ClaimListing>,
+
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: Listing seller; receives the listing rent
+    #[account(mut)]
+    pub seller: AccountInfo<'info>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+}
+
+/// Closes a claim listing. The seller may always delist; once the seller no
+/// longer holds the claim, anyone (typically the current lender, who needs
+/// the listing PDA to list again) may close the stale listing.
+pub fn delist_loan_claim(ctx: Context<DelistLoanClaim>) -> Result<()> {
+    let seller = ctx.accounts.listing.seller;
+    if ctx.accounts.caller.key() != seller {
+        let lender = current_lender(
+            &ctx.accounts.loan_account,
+            ctx.accounts.offer_account.as_ref(),
+            ctx.accounts.claim_account.as_ref(),
+        )?;
+        require_keys_neq!(lender, seller, ErrorCode::Unauthorized);
+    }
+
+    // ClaimListing is closed by the `close = seller` constraint.
+    Ok(())
+}
+
+/// Current holder of the loan's claim token, or `None` if no claim was
+/// minted. `claim` must be the token account holding it.
+pub(crate) fn claim_holder(loan: &LoanAccount, claim: Option<&Account<TokenAccount>>) -> Result<Option<Pubkey>> {
+    if loan.claim_mint == Pubkey::default() {
+        return Ok(None);
+    }
+    let claim = claim.ok_or(ErrorCode::InvalidLoanClaim)?;
+    require_keys_eq!(claim.mint, loan.claim_mint, ErrorCode::InvalidLoanClaim);
+    require!(claim.amount == 1, ErrorCode::InvalidLoanClaim);
+    Ok(Some(claim.owner))
+}
+
+/// The loan's current lender: the claim holder, the direct creditor, or the
+/// lender of the backing offer.
+pub(crate) fn current_lender(
+    loan: &LoanAccount,
+    offer: Option<&Account<OfferAccount>>,
+    claim: Option<&Account<TokenAccount>>,
+) -> Result<Pubkey> {
+    if let Some(holder) = claim_holder(loan, claim)? {
+        return Ok(holder);
+    }
+    if loan.creditor != Pubkey::default() {
+        return Ok(loan.creditor);
+    }
+    let offer = offer.ok_or(ErrorCode::OfferMismatch)?;
+    require_keys_eq!(offer.key(), loan.lender_source, ErrorCode::OfferMismatch);
+    Ok(offer.lender)
+}
+
+/// Checks that `signer` is the loan's current lender.
+pub(crate) fn check_lender_signer(
+    loan: &LoanAccount,
+    signer: &Pubkey,
+    offer: Option<&Account<OfferAccount>>,
+    claim: Option<&Account<TokenAccount>>,
+) -> Result<()> {
+    require_keys_eq!(*signer, current_lender(loan, offer, claim)?, ErrorCode::Unauthorized);
+    Ok(())
+}
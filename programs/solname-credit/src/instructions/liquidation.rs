@@ -1,8 +1,10 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use crate::state::{LoanAccount, LoanStatus, AuctionAccount, AuctionStatus, GlobalState};
-use crate::constants::{AUCTION_SEED, GLOBAL_SEED};
+use crate::state::{LoanAccount, LoanStatus, LoanType, AuctionAccount, AuctionStatus, GlobalState, OfferAccount, PoolState};
+use crate::constants::{AUCTION_SEED, GLOBAL_SEED, NAME_SERVICE_ID};
 use crate::error::ErrorCode;
+use crate::sns;
+use crate::instructions::borrow::{check_creditor_destination, credit_pool};
 
 #[derive(Accounts)]
 pub struct EnterGrace<'info> {
@@ -22,7 +24,8 @@ pub fn enter_grace(ctx: Context<EnterGrace>) -> Result<()> {
     let loan = &mut ctx.accounts.loan_account;
     let now = ctx.accounts.clock.unix_timestamp;
 
-    require!(now >= loan.due_ts, ErrorCode::LoanNotDue);
+    // Default on final maturity, or earlier on a missed installment.
+    require!(now >= loan.due_ts || loan.installment_overdue(now), ErrorCode::LoanNotDue);
 
     loan.status = LoanStatus::Grace;
     loan.grace_end_ts = now + ctx.accounts.global_state.grace_period_seconds;
@@ -75,6 +78,8 @@ pub fn start_auction(ctx: Context<StartAuction>) -> Result<()> {
     auction.end_price = loan.principal_amount;
 
     auction.status = AuctionStatus::Live;
+    auction.domain = Pubkey::default();
+    auction.mint = loan.mint;
     auction.bump = ctx.bumps.auction_account;
 
     loan.status = LoanStatus::AuctionLive;
@@ -82,6 +87,70 @@ pub fn start_auction(ctx: Context<StartAuction>) -> Result<()> {
     Ok(())
 }
 
+#[derive(Accounts)]
+#[instruction(domain: Pubkey)]
+pub struct StartLotAuction<'info> {
+    #[account(
+        mut,
+        constraint = loan_account.status == LoanStatus::Grace
+            || (loan_account.status == LoanStatus::AuctionLive && loan_account.auction_lots > 0)
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(
+        init,
+        seeds = [AUCTION_SEED, loan_account.key().as_ref(), domain.as_ref()],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<AuctionAccount>()
+    )]
+    pub auction_account: Account<'info, AuctionAccount>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Auctions one of a defaulted loan's domains on its own, priced on an equal
+/// share of the amount due. Once used, the remaining domains must also be sold
+/// this way instead of through `start_auction`, until the proceeds cover the
+/// debt and `settle_auction` returns the rest to the borrower.
+pub fn start_lot_auction(ctx: Context<StartLotAuction>, domain: Pubkey) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let auction = &mut ctx.accounts.auction_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    require!(now >= loan.grace_end_ts, ErrorCode::LoanNotDue);
+    loan.mark_lot(&domain)?;
+
+    let share = loan.amount_due(now)?.div_ceil(loan.domain_count() as u64);
+
+    auction.loan = loan.key();
+    auction.start_ts = now;
+    auction.end_ts = now + ctx.accounts.global_state.auction_duration_seconds;
+    auction.min_bid = share;
+    auction.highest_bid = 0;
+    auction.highest_bidder = Pubkey::default();
+
+    auction.start_price = share.checked_mul(2).ok_or(ErrorCode::MathOverflow)?;
+    auction.end_price = share;
+
+    auction.status = AuctionStatus::Live;
+    auction.domain = domain;
+    auction.mint = loan.mint;
+    auction.bump = ctx.bumps.auction_account;
+
+    loan.auction_lots += 1;
+    loan.status = LoanStatus::AuctionLive;
+
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct PlaceBid<'info> {
     #[account(mut)]
@@ -104,9 +173,12 @@ pub struct PlaceBid<'info> {
     // Or we init it if needed? No, cumbersome.
     // Let's assume the client passes a vault derived from [AUCTION_SEED, loan_key, "vault"]
 
-    // Using unchecked for vault simplicity in this patch, but normally:
-    // #[account(mut, seeds = [AUCTION_SEED, auction_account.loan.as_ref(), b"vault"], bump)]
-    #[account(mut)]
+    /// Token account of the auction, in the loan's mint; pays the lender at settlement
+    #[account(
+        mut,
+        token::authority = auction_account,
+        constraint = auction_vault.mint == auction_account.mint @ ErrorCode::MintMismatch
+    )]
     pub auction_vault: Account<'info, TokenAccount>,
 
     // Optional previous bidder account to refund
@@ -157,7 +229,11 @@ pub struct BuyItNow<'info> {
     #[account(mut)]
     pub buyer_usdc: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        token::authority = auction_account,
+        constraint = auction_vault.mint == auction_account.mint @ ErrorCode::MintMismatch
+    )]
     pub auction_vault: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
@@ -207,33 +283,98 @@ pub struct SettleAuction<'info> {
 
     #[account(
         mut,
-        constraint = auction_account.status == AuctionStatus::Ended
+        constraint = auction_account.status == AuctionStatus::Ended,
+        constraint = auction_account.loan == loan_account.key()
     )]
     pub auction_account: Account<'info, AuctionAccount>,
 
-    #[account(mut)]
+    #[account(mut, constraint = loan_account.status == LoanStatus::AuctionLive)]
     pub loan_account: Account<'info, LoanAccount>,
 
+    /// Required for P2P loans: the offer backing the loan.
+    #[account(mut)]
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required for Pool loans: the funding pool.
+    #[account(mut)]
+    pub pool_state: Option<Account<'info, PoolState>>,
+
+    /// Holds the winning bid
+    #[account(
+        mut,
+        token::authority = auction_account,
+        constraint = auction_vault.mint == auction_account.mint @ ErrorCode::MintMismatch
+    )]
+    pub auction_vault: Account<'info, TokenAccount>,
+
+    /// Current lender's token account; see `check_creditor_destination`
+    #[account(mut)]
+    pub destination_vault: Account<'info, TokenAccount>,
+
+    /// Borrower's token account; receives proceeds beyond the amount due
+    #[account(
+        mut,
+        constraint = borrower_usdc.owner == loan_account.borrower @ ErrorCode::Unauthorized,
+        constraint = borrower_usdc.mint == loan_account.mint @ ErrorCode::MintMismatch
+    )]
+    pub borrower_usdc: Account<'info, TokenAccount>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
     /// CHECK: PDA owning domain
     #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
     pub escrow_pda: AccountInfo<'info>,
 
-    /// CHECK: Domain Registry
+    /// CHECK: Domain sold by the auction (the primary domain for a whole
+    /// lot); checked in the handler
     #[account(mut)]
     pub domain_registry: AccountInfo<'info>,
 
-    /// CHECK: Winner
-    #[account(constraint = winner.key() == auction_account.highest_bidder)]
+    /// CHECK: V1 SOL record of the domain, whether or not it exists;
+    /// derivation checked in the handler
+    #[account(mut)]
+    pub sol_record: AccountInfo<'info>,
+
+    /// CHECK: Winner; receives the SOL record rent
+    #[account(mut, constraint = winner.key() == auction_account.highest_bidder)]
     pub winner: AccountInfo<'info>,
 
     /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
     pub name_service_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+    // remaining_accounts, whole-lot auctions of basket loans only: a
+    // (domain, V1 SOL record) pair per basket domain, in basket order (writable).
+    // Per-domain auctions whose proceeds cover the debt: each domain not yet
+    // auctioned, primary first, then in basket order (writable).
 }
 
-pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+/// Hands the auctioned domain(s) to the winner and pays the winning bid to
+/// the loan's current lender: the claim holder once a claim was minted,
+/// otherwise the direct creditor, the offer vault or the pool vault. Only the
+/// amount due goes to the lender; the rest of the bid goes to the borrower,
+/// and once the debt is covered so do the domains not yet auctioned.
+pub fn settle_auction<'info>(ctx: Context<'_, '_, 'info, 'info, SettleAuction<'info>>) -> Result<()> {
     let loan = &mut ctx.accounts.loan_account;
+    let auction = &ctx.accounts.auction_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    check_creditor_destination(
+        loan,
+        &ctx.accounts.destination_vault,
+        ctx.accounts.offer_account.as_ref(),
+        ctx.accounts.claim_account.as_ref(),
+        ctx.program_id,
+    )?;
+
+    let whole_lot = auction.domain == Pubkey::default();
+    let lot_domain = if whole_lot { loan.domain_registry } else { auction.domain };
+    require_keys_eq!(ctx.accounts.domain_registry.key(), lot_domain, ErrorCode::BasketMismatch);
 
-    // Transfer Domain to Winner
     let loan_key = loan.key();
     let seeds = &[
         b"escrow",
@@ -242,47 +383,207 @@ pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
     ];
     let signer = &[&seeds[..]];
 
-    let transfer_instruction_data = vec![1]; // Tag for Transfer
-    let transfer_accounts = vec![
-        AccountMeta::new(ctx.accounts.domain_registry.key(), false),
-        AccountMeta::new(ctx.accounts.winner.key(), false),
-        AccountMeta::new_readonly(ctx.accounts.escrow_pda.key(), true),
-    ];
-
-    let ix_transfer = anchor_lang::solana_program::instruction::Instruction {
-        program_id: ctx.accounts.name_service_program.key(),
-        accounts: transfer_accounts,
-        data: [transfer_instruction_data, ctx.accounts.winner.key().to_bytes().to_vec()].concat(),
-    };
+    scrub_sol_record(
+        loan,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.sol_record,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.winner,
+        signer,
+    )?;
 
-    anchor_lang::solana_program::program::invoke_signed(
-        &ix_transfer,
-        &[
-            ctx.accounts.domain_registry.to_account_info(),
-            ctx.accounts.winner.to_account_info(),
-            ctx.accounts.escrow_pda.to_account_info(),
-        ],
-        signer
+    // Transfer Domain to Winner
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.winner.key(),
+        signer,
     )?;
 
-    // 3. Clear SOL Record (V1 Delete)
-    // The "SOL Record" is a specific Name Registry Account.
-    // The name of the record is derived: HASH_PREFIX + sha256(class_name . parent_name) usually.
-    // For SOL record V1, it's a specific class or parent?
-    // Actually, "SOL Record V1" is usually a record under the domain with class = Pubkey::default().
-    // The key is to delete the record account.
-    // Since we don't have the record account passed in `Accounts`, we can't delete it here.
-    // FIX: We need to pass the SOL Record Account to this instruction to delete it.
-    // For MVP blind mode, we will emit an event or log that the client MUST delete it.
-    // However, the PRD says "Must clean".
-    // Let's assume the domain registry itself was the collateral. If the record is separate, it's a different account.
-    // If the record is inside the data (deprecated), we overwrite it.
-    // Spl-name-service uses separate accounts for records.
-    // We will emit a log instruction to signify this requirement.
-
-    msg!("Action Required: Client must invoke Delete instruction on SOL Record account if present.");
+    // A whole lot also carries the basket domains, each scrubbed the same way
+    if whole_lot {
+        let pairs = ctx.remaining_accounts.chunks_exact(2);
+        require!(
+            pairs.len() == loan.basket().len() && pairs.remainder().is_empty(),
+            ErrorCode::BasketMismatch
+        );
+        let basket = loan.basket().to_vec();
+        for (pair, key) in pairs.zip(&basket) {
+            let (domain, sol_record) = (&pair[0], &pair[1]);
+            require_keys_eq!(domain.key(), *key, ErrorCode::BasketMismatch);
+            scrub_sol_record(
+                loan,
+                &ctx.accounts.name_service_program,
+                sol_record,
+                &ctx.accounts.escrow_pda,
+                &ctx.accounts.system_program.to_account_info(),
+                domain,
+                &ctx.accounts.winner,
+                signer,
+            )?;
+            sns::transfer(
+                &ctx.accounts.name_service_program,
+                domain,
+                &ctx.accounts.escrow_pda,
+                &ctx.accounts.winner.key(),
+                signer,
+            )?;
+        }
+    }
+
+    // Pay the winning bid out of the auction vault
+    let (principal, interest, surplus) = loan.apply_auction_proceeds(auction.highest_bid, now)?;
+    credit_pool(loan, ctx.accounts.pool_state.as_mut(), principal, interest)?;
+
+    let auction_bump = [auction.bump];
+    let auction_seeds: &[&[u8]] = if whole_lot {
+        &[AUCTION_SEED, loan_key.as_ref(), &auction_bump]
+    } else {
+        &[AUCTION_SEED, loan_key.as_ref(), lot_domain.as_ref(), &auction_bump]
+    };
+    let auction_signer = &[auction_seeds];
+    for (to, amount) in [
+        (&ctx.accounts.destination_vault, principal + interest),
+        (&ctx.accounts.borrower_usdc, surplus),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.auction_vault.to_account_info(),
+            to: to.to_account_info(),
+            authority: ctx.accounts.auction_account.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, auction_signer);
+        token::transfer(cpi_ctx, amount)?;
+    }
+    ctx.accounts.auction_account.status = AuctionStatus::Settled;
+
+    if !whole_lot {
+        loan.lots_settled += 1;
+
+        // Debt covered: the domains not yet auctioned go back to the borrower
+        if loan.amount_due(now)? == 0 {
+            let unsold: Vec<Pubkey> = loan.unsold_lots().copied().collect();
+            require!(ctx.remaining_accounts.len() >= unsold.len(), ErrorCode::BasketMismatch);
+            for (domain, key) in ctx.remaining_accounts.iter().zip(&unsold) {
+                require_keys_eq!(domain.key(), *key, ErrorCode::BasketMismatch);
+                sns::transfer(
+                    &ctx.accounts.name_service_program,
+                    domain,
+                    &ctx.accounts.escrow_pda,
+                    &loan.borrower,
+                    signer,
+                )?;
+                loan.mark_lot(key)?;
+                loan.lots_settled += 1;
+            }
+        }
+        if loan.lots_settled < loan.domain_count() {
+            return Ok(());
+        }
+    }
+
+    // Principal the auctions did not recover is a loss to the pool
+    if loan.loan_type == LoanType::Pool {
+        if let Some(pool) = ctx.accounts.pool_state.as_mut() {
+            pool.record_loss(loan.principal_amount)?;
+        }
+    }
+
+    if loan.loan_type == LoanType::P2P && loan.creditor == Pubkey::default() {
+        let offer = ctx.accounts.offer_account.as_mut().ok_or(ErrorCode::OfferMismatch)?;
+        require_keys_eq!(offer.key(), loan.lender_source, ErrorCode::OfferMismatch);
+        offer.release_loan()?;
+    }
 
     loan.status = LoanStatus::Settled;
 
     Ok(())
 }
+
+/// Deletes `domain`'s SOL record while the escrow still owns the domain, so
+/// the winner does not inherit a record paying the defaulted borrower. Flags
+/// the domain for `cleanup_record` only if the record survived the deletion.
+#[allow(clippy::too_many_arguments)]
+fn scrub_sol_record<'info>(
+    loan: &mut LoanAccount,
+    name_service: &AccountInfo<'info>,
+    sol_record: &AccountInfo<'info>,
+    escrow_pda: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    domain: &AccountInfo<'info>,
+    refund_target: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require_keys_eq!(sol_record.key(), sns::record_v1_key(domain.key, "SOL"), ErrorCode::SolRecordMismatch);
+    sns::delete_child_record(name_service, sol_record, escrow_pda, system_program, domain, refund_target, signer_seeds)?;
+    if sol_record.lamports() > 0 && *sol_record.owner == NAME_SERVICE_ID {
+        loan.record_cleanup_mask |= loan.domain_bit(domain.key).ok_or(ErrorCode::BasketMismatch)?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CleanupRecord<'info> {
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = loan_account.status == LoanStatus::Settled
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: One of the loan's domains whose SOL record settlement left
+    /// behind; must now be owned by the winner
+    #[account(constraint = loan_account.needs_record_cleanup(domain_registry.key) @ ErrorCode::BasketMismatch)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: V1 SOL record of the domain; derivation checked in the handler
+    #[account(mut)]
+    pub sol_record: AccountInfo<'info>,
+
+    /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Deletes the SOL record left behind by a settlement that could not clear it,
+/// unless the winner already pointed it away from the borrower's `record_payout`.
+pub fn cleanup_record(ctx: Context<CleanupRecord>) -> Result<()> {
+    let header = sns::NameRecordHeader::load(&ctx.accounts.domain_registry)?;
+    require_keys_eq!(header.owner, ctx.accounts.winner.key(), ErrorCode::Unauthorized);
+    require_keys_eq!(
+        ctx.accounts.sol_record.key(),
+        sns::record_v1_key(ctx.accounts.domain_registry.key, "SOL"),
+        ErrorCode::SolRecordMismatch
+    );
+
+    let loan = &ctx.accounts.loan_account;
+    let current = sns::sol_record_address(&ctx.accounts.sol_record)?;
+    let stale = loan.record_payout == Pubkey::default() || current == Some(loan.record_payout);
+
+    let winner = ctx.accounts.winner.to_account_info();
+    if stale {
+        sns::delete_child_record(
+            &ctx.accounts.name_service_program,
+            &ctx.accounts.sol_record,
+            &winner,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.domain_registry,
+            &winner,
+            &[],
+        )?;
+    }
+
+    let loan = &mut ctx.accounts.loan_account;
+    let bit = loan.domain_bit(ctx.accounts.domain_registry.key).ok_or(ErrorCode::BasketMismatch)?;
+    loan.record_cleanup_mask &= !bit;
+    Ok(())
+}
@@ -3,3 +3,9 @@ pub mod lp;
 pub mod p2p;
 pub mod borrow;
 pub mod liquidation;
+pub mod request;
+pub mod signed_offer;
+pub mod claim;
+pub mod buyout;
+pub mod record;
+pub mod basket;
@@ -1,8 +1,10 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, CloseAccount};
-use crate::state::OfferAccount;
-use crate::constants::{OFFER_SEED, VAULT_SEED};
+use crate::state::{OfferAccount, LenderProfile, LoanAccount, LoanStatus};
+use crate::constants::{OFFER_SEED, VAULT_SEED, LENDER_SEED, OPEN_ENDED_DUE_TS};
 use crate::error::ErrorCode;
+use crate::instructions::claim::check_lender_signer;
+use crate::OfferParams;
 
 #[derive(Accounts)]
 #[instruction(nonce: u64)]
@@ -38,25 +40,49 @@ pub struct CreateOffer<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
-pub fn create_offer(
-    ctx: Context<CreateOffer>,
-    nonce: u64,
-    principal: u64,
-    apr_bps: u16,
-    duration_seconds: i64,
-    offer_expiry: i64
-) -> Result<()> {
+pub fn create_offer(ctx: Context<CreateOffer>, nonce: u64, params: OfferParams) -> Result<()> {
+    require!(
+        params.max_per_loan > 0 && params.max_per_loan <= params.total_budget,
+        ErrorCode::InvalidOfferTerms
+    );
+    if params.callable {
+        require!(
+            params.notice_period_seconds > 0 && params.installment_count == 0,
+            ErrorCode::InvalidOfferTerms
+        );
+    } else {
+        require!(params.duration_seconds > 0, ErrorCode::InvalidOfferTerms);
+    }
+    require!(params.min_interest_seconds >= 0, ErrorCode::InvalidOfferTerms);
+    if params.installment_count > 0 {
+        require!(
+            params.installment_period > 0
+                && params.installment_period.checked_mul(params.installment_count as i64) == Some(params.duration_seconds),
+            ErrorCode::InvalidOfferTerms
+        );
+    }
+
     let offer_account = &mut ctx.accounts.offer_account;
     offer_account.lender = ctx.accounts.lender.key();
-    offer_account.principal = principal;
-    offer_account.apr_bps = apr_bps;
-    offer_account.duration_seconds = duration_seconds;
-    offer_account.offer_expiry = offer_expiry;
+    offer_account.total_budget = params.total_budget;
+    offer_account.max_per_loan = params.max_per_loan;
+    offer_account.remaining_capacity = params.total_budget;
+    offer_account.active_loans = 0;
+    offer_account.apr_bps = params.apr_bps;
+    offer_account.duration_seconds = params.duration_seconds;
+    offer_account.offer_expiry = params.offer_expiry;
+    offer_account.max_rollovers = params.max_rollovers;
+    offer_account.installment_period = params.installment_period;
+    offer_account.installment_count = params.installment_count;
+    offer_account.callable = params.callable;
+    offer_account.notice_period_seconds = params.notice_period_seconds;
+    offer_account.min_interest_seconds = params.min_interest_seconds;
+    offer_account.origination_interest_bps = params.origination_interest_bps;
     offer_account.is_active = true;
     offer_account.nonce = nonce;
     offer_account.bump = ctx.bumps.offer_account;
 
-    // Transfer principal to vault
+    // Transfer the full budget to vault
     let cpi_accounts = Transfer {
         from: ctx.accounts.lender_usdc.to_account_info(),
         to: ctx.accounts.offer_vault.to_account_info(),
@@ -64,7 +90,7 @@ pub fn create_offer(
     };
     let cpi_program = ctx.accounts.token_program.to_account_info();
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, principal)?;
+    token::transfer(cpi_ctx, params.total_budget)?;
 
     Ok(())
 }
@@ -74,11 +100,7 @@ pub struct CancelOffer<'info> {
     #[account(mut)]
     pub lender: Signer<'info>,
 
-    #[account(
-        mut,
-        has_one = lender,
-        close = lender
-    )]
+    #[account(mut, has_one = lender)]
     pub offer_account: Account<'info, OfferAccount>,
 
     #[account(
@@ -96,8 +118,152 @@ pub struct CancelOffer<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Withdraws everything held in the offer vault (unused budget plus any
+/// repayments) and stops new draws. The offer and its vault are only closed
+/// once no loans backed by the offer remain outstanding, since repayments
+/// still flow back into the vault.
 pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
-    let offer_account = &ctx.accounts.offer_account;
+    let closed = withdraw_offer(
+        &mut ctx.accounts.offer_account,
+        &ctx.accounts.offer_vault,
+        &ctx.accounts.lender,
+        &ctx.accounts.lender_usdc,
+        &ctx.accounts.token_program,
+    )?;
+
+    if closed {
+        ctx.accounts.offer_account.close(ctx.accounts.lender.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CallLoan<'info> {
+    pub lender: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = loan_account.status == LoanStatus::Active
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// Required for offer-backed loans: the offer backing the loan.
+    pub offer_account: Option<Account<'info, OfferAccount>>,
+
+    /// Required once a claim token was minted: the token account holding it.
+    pub claim_account: Option<Account<'info, TokenAccount>>,
+
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Calls a callable loan: it falls due after the notice period, after which
+/// the usual `enter_grace` -> `start_auction` path applies.
+pub fn call_loan(ctx: Context<CallLoan>) -> Result<()> {
+    check_lender_signer(
+        &ctx.accounts.loan_account,
+        &ctx.accounts.lender.key(),
+        ctx.accounts.offer_account.as_ref(),
+        ctx.accounts.claim_account.as_ref(),
+    )?;
+
+    let loan = &mut ctx.accounts.loan_account;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    require!(loan.callable, ErrorCode::LoanNotCallable);
+    require!(loan.due_ts == OPEN_ENDED_DUE_TS, ErrorCode::LoanNotCallable);
+
+    loan.due_ts = now.checked_add(loan.notice_period_seconds).ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelOffers<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(mut)]
+    pub lender_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Cancels many offers in one transaction. `remaining_accounts` holds
+/// `(offer_account, offer_vault)` pairs, all owned by `lender`.
+pub fn cancel_offers<'info>(ctx: Context<'_, '_, 'info, 'info, CancelOffers<'info>>) -> Result<()> {
+    let pairs = ctx.remaining_accounts.chunks_exact(2);
+    require!(pairs.len() > 0 && pairs.remainder().is_empty(), ErrorCode::OfferMismatch);
+
+    for pair in pairs {
+        let mut offer_account = Account::<OfferAccount>::try_from(&pair[0])?;
+        let offer_vault = Account::<TokenAccount>::try_from(&pair[1])?;
+
+        require_keys_eq!(offer_account.lender, ctx.accounts.lender.key(), ErrorCode::Unauthorized);
+        let (vault_key, _) = Pubkey::find_program_address(
+            &[VAULT_SEED, offer_account.key().as_ref()],
+            ctx.program_id,
+        );
+        require_keys_eq!(offer_vault.key(), vault_key, ErrorCode::OfferMismatch);
+
+        let closed = withdraw_offer(
+            &mut offer_account,
+            &offer_vault,
+            &ctx.accounts.lender,
+            &ctx.accounts.lender_usdc,
+            &ctx.accounts.token_program,
+        )?;
+
+        if closed {
+            offer_account.close(ctx.accounts.lender.to_account_info())?;
+        } else {
+            offer_account.exit(ctx.program_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMinNonce<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [LENDER_SEED, lender.key().as_ref()],
+        bump,
+        payer = lender,
+        space = 8 + std::mem::size_of::<LenderProfile>()
+    )]
+    pub lender_profile: Account<'info, LenderProfile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Raises the lender's minimum valid nonce, invalidating every open offer
+/// and signed quote below it in one step.
+pub fn set_min_nonce(ctx: Context<SetMinNonce>, min_nonce: u64) -> Result<()> {
+    let profile = &mut ctx.accounts.lender_profile;
+    require!(min_nonce >= profile.min_nonce, ErrorCode::NonceInvalidated);
+
+    profile.lender = ctx.accounts.lender.key();
+    profile.min_nonce = min_nonce;
+    profile.bump = ctx.bumps.lender_profile;
+    Ok(())
+}
+
+/// Sweeps the offer vault to the lender and deactivates the offer. Closes
+/// the vault and returns true when the offer has no outstanding loans, in
+/// which case the caller must close the offer account itself.
+fn withdraw_offer<'info>(
+    offer_account: &mut Account<'info, OfferAccount>,
+    offer_vault: &Account<'info, TokenAccount>,
+    lender: &Signer<'info>,
+    lender_usdc: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> Result<bool> {
+    offer_account.is_active = false;
+    offer_account.remaining_capacity = 0;
 
     // Transfer funds back to lender
     let nonce_bytes = offer_account.nonce.to_le_bytes();
@@ -110,22 +276,25 @@ pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
     let signer = &[&seeds[..]];
 
     let cpi_accounts = Transfer {
-        from: ctx.accounts.offer_vault.to_account_info(),
-        to: ctx.accounts.lender_usdc.to_account_info(),
-        authority: ctx.accounts.offer_account.to_account_info(),
+        from: offer_vault.to_account_info(),
+        to: lender_usdc.to_account_info(),
+        authority: offer_account.to_account_info(),
     };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-    token::transfer(cpi_ctx, ctx.accounts.offer_vault.amount)?;
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, offer_vault.amount)?;
+
+    if offer_account.active_loans > 0 {
+        return Ok(false);
+    }
 
     // Close the vault
     let close_accounts = CloseAccount {
-        account: ctx.accounts.offer_vault.to_account_info(),
-        destination: ctx.accounts.lender.to_account_info(),
-        authority: ctx.accounts.offer_account.to_account_info(),
+        account: offer_vault.to_account_info(),
+        destination: lender.to_account_info(),
+        authority: offer_account.to_account_info(),
     };
-    let close_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), close_accounts, signer);
+    let close_ctx = CpiContext::new_with_signer(token_program.to_account_info(), close_accounts, signer);
     token::close_account(close_ctx)?;
 
-    Ok(())
+    Ok(true)
 }
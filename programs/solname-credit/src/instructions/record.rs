@@ -0,0 +1,274 @@
+use anchor_lang::prelude::*;
+use crate::state::{LoanAccount, LoanStatus, RecordSnapshot};
+use crate::constants::{LOAN_SEED, NAME_SERVICE_ID};
+use crate::error::ErrorCode;
+use crate::sns;
+
+/// V1 records a borrower may edit while the domain is escrowed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordKind {
+    Sol,
+    Url,
+    Ipfs,
+    Twitter,
+    Discord,
+    Github,
+    Telegram,
+    Email,
+    Pic,
+}
+
+impl RecordKind {
+    /// Record name as used in the V1 record derivation.
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordKind::Sol => "SOL",
+            RecordKind::Url => "url",
+            RecordKind::Ipfs => "IPFS",
+            RecordKind::Twitter => "twitter",
+            RecordKind::Discord => "discord",
+            RecordKind::Github => "github",
+            RecordKind::Telegram => "telegram",
+            RecordKind::Email => "email",
+            RecordKind::Pic => "pic",
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateRecord<'info> {
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::Active || loan_account.status == LoanStatus::Grace
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: PDA owning domain
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Must be the domain escrowed by this loan
+    #[account(address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: V1 record of the domain; derivation checked in the handler
+    #[account(mut)]
+    pub record: AccountInfo<'info>,
+
+    /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Writes `data` at `offset` into one of the domain's whitelisted records,
+/// signed by the escrow. Only record accounts can be targeted, so the
+/// domain's own header (and its owner) stays untouched. The record joins the
+/// loan's snapshot, so repay checks it and hands it back. A SOL record write
+/// must set the address at offset 0, which becomes the loan's `record_payout`.
+pub fn update_record(ctx: Context<UpdateRecord>, kind: RecordKind, offset: u32, data: Vec<u8>) -> Result<()> {
+    let loan = &mut ctx.accounts.loan_account;
+    let record = &ctx.accounts.record;
+
+    require_keys_eq!(
+        record.key(),
+        sns::record_v1_key(&loan.domain_registry, kind.name()),
+        ErrorCode::RecordMismatch
+    );
+    require_keys_eq!(*record.owner, NAME_SERVICE_ID, ErrorCode::RecordMismatch);
+    let end = (offset as usize).checked_add(data.len()).ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        !data.is_empty() && end <= record.data_len().saturating_sub(sns::NAME_RECORD_HEADER_LEN),
+        ErrorCode::InvalidRecordWrite
+    );
+    if kind == RecordKind::Sol {
+        require!(offset == 0 && data.len() >= 32, ErrorCode::InvalidRecordWrite);
+    }
+
+    let loan_key = loan.key();
+    let seeds = &[
+        b"escrow",
+        loan_key.as_ref(),
+        &[ctx.bumps.escrow_pda]
+    ];
+    let signer = &[&seeds[..]];
+
+    // Records created before escrow are still owned by the borrower's
+    // wallet; take them over as parent owner so the escrow can sign.
+    if sns::NameRecordHeader::load(record)?.owner != ctx.accounts.escrow_pda.key() {
+        sns::transfer_by_parent(
+            &ctx.accounts.name_service_program,
+            record,
+            &ctx.accounts.escrow_pda,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.domain_registry,
+            &ctx.accounts.escrow_pda.key(),
+            signer,
+        )?;
+    }
+
+    sns::update(
+        &ctx.accounts.name_service_program,
+        record,
+        &ctx.accounts.escrow_pda,
+        offset,
+        &data,
+        signer,
+    )?;
+
+    loan.record_snapshot.track(record.key(), sns::content_hash(record)?)?;
+    if kind == RecordKind::Sol {
+        loan.record_payout = Pubkey::new_from_array(data[..32].try_into().unwrap());
+        loan.record_confirmed = true;
+    }
+
+    Ok(())
+}
+
+/// Points the domain's existing SOL record at `address`, signed by
+/// `parent_owner` (the current domain owner), taking the record over first
+/// if someone else owns it. Returns false when the record does not exist.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_sol_record<'info>(
+    name_service: &AccountInfo<'info>,
+    sol_record: &AccountInfo<'info>,
+    parent_owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    domain_registry: &AccountInfo<'info>,
+    address: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<bool> {
+    require_keys_eq!(
+        sol_record.key(),
+        sns::record_v1_key(domain_registry.key, RecordKind::Sol.name()),
+        ErrorCode::SolRecordMismatch
+    );
+    match sns::sol_record_address(sol_record)? {
+        None => return Ok(false),
+        Some(current) if current == *address => return Ok(true),
+        Some(_) => {}
+    }
+
+    if sns::NameRecordHeader::load(sol_record)?.owner != *parent_owner.key {
+        sns::transfer_by_parent(
+            name_service,
+            sol_record,
+            parent_owner,
+            system_program,
+            domain_registry,
+            parent_owner.key,
+            signer_seeds,
+        )?;
+    }
+    sns::update(name_service, sol_record, parent_owner, 0, address.as_ref(), signer_seeds)?;
+    Ok(true)
+}
+
+/// Points the domain's SOL record (when given) at `payout`, which becomes the
+/// loan's `record_payout`, then snapshots the domain and `records`. Runs when
+/// a domain is escrowed, signed by the borrower who still owns it.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn escrow_records<'info>(
+    loan: &mut LoanAccount,
+    name_service: &AccountInfo<'info>,
+    domain_registry: &AccountInfo<'info>,
+    borrower: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    escrow: &Pubkey,
+    sol_record: Option<&AccountInfo<'info>>,
+    payout: &Pubkey,
+    records: &[AccountInfo<'info>],
+) -> Result<()> {
+    loan.record_confirmed = match sol_record {
+        Some(sol_record) => write_sol_record(
+            name_service,
+            sol_record,
+            borrower,
+            system_program,
+            domain_registry,
+            payout,
+            &[],
+        )?,
+        None => false,
+    };
+    loan.record_payout = *payout;
+    snapshot_records(
+        &mut loan.record_snapshot,
+        name_service,
+        domain_registry,
+        borrower,
+        system_program,
+        escrow,
+        records,
+    )
+}
+
+/// Snapshots the domain and its `records`, moving the records into escrow so
+/// they cannot drift while the loan is open.
+fn snapshot_records<'info>(
+    snapshot: &mut RecordSnapshot,
+    name_service: &AccountInfo<'info>,
+    domain_registry: &AccountInfo<'info>,
+    borrower: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    escrow: &Pubkey,
+    records: &[AccountInfo<'info>],
+) -> Result<()> {
+    snapshot.header_hash = sns::content_hash(domain_registry)?;
+    for record in records {
+        require_keys_eq!(*record.owner, NAME_SERVICE_ID, ErrorCode::RecordMismatch);
+        let header = sns::NameRecordHeader::load(record)?;
+        require_keys_eq!(header.parent_name, domain_registry.key(), ErrorCode::RecordMismatch);
+        if header.owner != *escrow {
+            sns::transfer_by_parent(name_service, record, borrower, system_program, domain_registry, escrow, &[])?;
+        }
+        snapshot.push(record.key(), sns::content_hash(record)?)?;
+    }
+    Ok(())
+}
+
+/// Checks the domain (when snapshotted at setup) and the snapshotted
+/// `records` (in snapshot order) against the snapshot. Must run before the
+/// program writes any of them on the way out.
+pub(crate) fn check_snapshot(
+    snapshot: &RecordSnapshot,
+    domain_registry: &AccountInfo,
+    records: &[AccountInfo],
+) -> Result<()> {
+    if snapshot.is_taken() {
+        require!(
+            sns::content_hash(domain_registry)? == snapshot.header_hash,
+            ErrorCode::RecordSnapshotMismatch
+        );
+    }
+    require!(records.len() >= snapshot.records().len(), ErrorCode::RecordSnapshotMismatch);
+    for ((record, key), hash) in records.iter().zip(snapshot.records()).zip(&snapshot.record_hashes) {
+        require_keys_eq!(record.key(), *key, ErrorCode::RecordSnapshotMismatch);
+        require!(sns::content_hash(record)? == *hash, ErrorCode::RecordSnapshotMismatch);
+    }
+    Ok(())
+}
+
+/// Transfers the escrow-held snapshotted `records` to `recipient` without
+/// checking their contents.
+pub(crate) fn return_records<'info>(
+    snapshot: &RecordSnapshot,
+    name_service: &AccountInfo<'info>,
+    escrow_pda: &AccountInfo<'info>,
+    recipient: &Pubkey,
+    records: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    for record in records.iter().filter(|r| snapshot.records().contains(r.key)) {
+        if sns::NameRecordHeader::load(record)?.owner == escrow_pda.key() {
+            sns::transfer(name_service, record, escrow_pda, recipient, signer_seeds)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{LoanAccount, LoanStatus, LoanType, LoanRequest, GlobalState};
+use crate::constants::{GLOBAL_SEED, LOAN_SEED, REQUEST_SEED, NAME_SERVICE_ID};
+use crate::error::ErrorCode;
+use crate::sns;
+use crate::admission::{admit_domain, check_no_registrar};
+use crate::instructions::basket::release_basket;
+use crate::instructions::record::{escrow_records, return_records};
+
+#[derive(Accounts)]
+pub struct CreateRequest<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    /// CHECK: Validated manualy via data inspection
+    #[account(mut, owner = NAME_SERVICE_ID @ ErrorCode::DomainNotNameService)]
+    pub domain_registry: AccountInfo<'info>,
+
+    #[account(
+        init,
+        seeds = [LOAN_SEED, domain_registry.key().as_ref()],
+        bump,
+        payer = borrower,
+        space = 8 + std::mem::size_of::<LoanAccount>()
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    #[account(
+        init,
+        seeds = [REQUEST_SEED, loan_account.key().as_ref()],
+        bump,
+        payer = borrower,
+        space = 8 + std::mem::size_of::<LoanRequest>()
+    )]
+    pub loan_request: Account<'info, LoanRequest>,
+
+    /// CHECK: This PDA will become the new owner of the domain
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Name Service Program
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+
+    #[account(seeds = [GLOBAL_SEED], bump)]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: V1 SOL record of the domain; set to `payout` when present
+    #[account(mut)]
+    pub sol_record: Option<AccountInfo<'info>>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts[0]: subdomain registrar PDA of the domain. The rest:
+    // record accounts of the domain to escrow and snapshot (writable).
+}
+
+pub fn create_request<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateRequest<'info>>,
+    principal: u64,
+    max_apr_bps: u16,
+    duration_seconds: i64,
+    expiry: i64,
+    payout: Pubkey
+) -> Result<()> {
+    require!(principal > 0 && duration_seconds > 0, ErrorCode::InvalidOfferTerms);
+
+    admit_domain(&ctx.accounts.domain_registry, &ctx.accounts.borrower.key())?;
+    check_no_registrar(
+        &ctx.accounts.domain_registry.key(),
+        &ctx.accounts.global_state.subdomain_registrar_program,
+        ctx.remaining_accounts.first(),
+    )?;
+
+    // Same SOL record and snapshot handling as setup_collateral
+    escrow_records(
+        &mut ctx.accounts.loan_account,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        ctx.accounts.sol_record.as_ref(),
+        &payout,
+        ctx.remaining_accounts.get(1..).unwrap_or_default(),
+    )?;
+
+    // CPI Transfer to Escrow
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.borrower.to_account_info(),
+        &ctx.accounts.escrow_pda.key(),
+        &[],
+    )?;
+
+    let request_key = ctx.accounts.loan_request.key();
+
+    let loan_account = &mut ctx.accounts.loan_account;
+    loan_account.borrower = ctx.accounts.borrower.key();
+    loan_account.domain_registry = ctx.accounts.domain_registry.key();
+    loan_account.seed_domain = ctx.accounts.domain_registry.key();
+    loan_account.escrow_pda = ctx.accounts.escrow_pda.key();
+    loan_account.status = LoanStatus::SetupPending;
+    loan_account.loan_type = LoanType::P2P;
+    loan_account.lender_source = request_key;
+    loan_account.bump = ctx.bumps.loan_account;
+
+    let loan_request = &mut ctx.accounts.loan_request;
+    loan_request.borrower = ctx.accounts.borrower.key();
+    loan_request.loan = loan_account.key();
+    loan_request.mint = ctx.accounts.global_state.usdc_mint;
+    loan_request.principal = principal;
+    loan_request.max_apr_bps = max_apr_bps;
+    loan_request.duration_seconds = duration_seconds;
+    loan_request.expiry = expiry;
+    loan_request.bump = ctx.bumps.loan_request;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FillRequest<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [REQUEST_SEED, loan_account.key().as_ref()],
+        bump = loan_request.bump,
+        has_one = borrower,
+        close = borrower
+    )]
+    pub loan_request: Account<'info, LoanRequest>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.status == LoanStatus::SetupPending,
+        constraint = loan_account.lender_source == loan_request.key()
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: Receives the request rent on close
+    #[account(mut)]
+    pub borrower: AccountInfo<'info>,
+
+    #[account(mut, constraint = lender_usdc.mint == loan_request.mint @ ErrorCode::MintMismatch)]
+    pub lender_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = borrower_usdc.owner == borrower.key(),
+        constraint = borrower_usdc.mint == loan_request.mint @ ErrorCode::MintMismatch
+    )]
+    pub borrower_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Funds a borrower's request directly from the lender's token account.
+/// Any lender may fill at a rate up to the request maximum; the first fill
+/// wins and the request is closed. Requests are filled as bullet loans.
+pub fn fill_request(ctx: Context<FillRequest>, apr_bps: u16) -> Result<()> {
+    let request = &ctx.accounts.loan_request;
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    require!(now < request.expiry, ErrorCode::RequestExpired);
+    require!(apr_bps <= request.max_apr_bps, ErrorCode::RateAboveRequestMax);
+
+    let principal = request.principal;
+
+    let loan = &mut ctx.accounts.loan_account;
+    loan.principal_amount = principal;
+    loan.apr_bps = apr_bps;
+    loan.start_ts = now;
+    loan.due_ts = now.checked_add(request.duration_seconds).ok_or(ErrorCode::MathOverflow)?;
+    loan.duration_seconds = request.duration_seconds;
+    loan.status = LoanStatus::Active;
+    loan.lender_source = ctx.accounts.lender.key();
+    loan.creditor = ctx.accounts.lender.key();
+    loan.mint = request.mint;
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.lender_usdc.to_account_info(),
+        to: ctx.accounts.borrower_usdc.to_account_info(),
+        authority: ctx.accounts.lender.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, principal)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRequest<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [REQUEST_SEED, loan_account.key().as_ref()],
+        bump = loan_request.bump,
+        has_one = borrower,
+        close = borrower
+    )]
+    pub loan_request: Account<'info, LoanRequest>,
+
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::SetupPending,
+        constraint = loan_account.lender_source == loan_request.key(),
+        close = borrower
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    /// CHECK: PDA owning domain
+    #[account(seeds = [b"escrow", loan_account.key().as_ref()], bump)]
+    pub escrow_pda: AccountInfo<'info>,
+
+    /// CHECK: Must be the domain escrowed by this loan
+    #[account(mut, address = loan_account.domain_registry)]
+    pub domain_registry: AccountInfo<'info>,
+
+    /// CHECK: Name Service
+    #[account(address = NAME_SERVICE_ID)]
+    pub name_service_program: AccountInfo<'info>,
+    // remaining_accounts: basket domains in basket order, then snapshotted
+    // record accounts to hand back (all writable).
+}
+
+pub fn cancel_request<'info>(ctx: Context<'_, '_, 'info, 'info, CancelRequest<'info>>) -> Result<()> {
+    // Return Domain
+    let loan_key = ctx.accounts.loan_account.key();
+    let seeds = &[
+        b"escrow",
+        loan_key.as_ref(),
+        &[ctx.bumps.escrow_pda]
+    ];
+    let signer = &[&seeds[..]];
+
+    sns::transfer(
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.domain_registry,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        signer,
+    )?;
+    let records = release_basket(
+        &ctx.accounts.loan_account,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        ctx.remaining_accounts,
+        signer,
+    )?;
+    return_records(
+        &ctx.accounts.loan_account.record_snapshot,
+        &ctx.accounts.name_service_program,
+        &ctx.accounts.escrow_pda,
+        &ctx.accounts.borrower.key(),
+        records,
+        signer,
+    )?;
+
+    // LoanRequest and LoanAccount are closed by their `close = borrower` constraints.
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions as ix_sysvar};
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use crate::state::{LoanAccount, LoanStatus, LoanType, UsedNonce, LenderProfile};
+use crate::constants::{LOAN_SEED, NONCE_SEED, DELEGATE_SEED, LENDER_SEED, SIGNED_OFFER_PREFIX};
+use crate::error::ErrorCode;
+
+/// Terms a lender signs off-chain. The signed message is
+/// `SIGNED_OFFER_PREFIX || program id || borsh(SignedOfferTerms)`, so a
+/// signature is only valid for this deployment. Signed offers fund
+/// bullet loans only; installment schedules need an `OfferAccount`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SignedOfferTerms {
+    pub lender: Pubkey,
+    pub mint: Pubkey,
+    pub principal: u64,
+    pub apr_bps: u16,
+    pub duration_seconds: i64,
+    pub nonce: u64,
+    pub expiry: i64,
+    pub domain: Option<Pubkey>,
+}
+
+impl SignedOfferTerms {
+    pub fn message(&self) -> Result<Vec<u8>> {
+        Ok([SIGNED_OFFER_PREFIX.to_vec(), crate::ID.to_bytes().to_vec(), self.try_to_vec()?].concat())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(terms: SignedOfferTerms)]
+pub struct TakeSignedOffer<'info> {
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    // The borrower runs setup_collateral in P2P mode with the nonce PDA as offer_id.
+    #[account(
+        mut,
+        seeds = [LOAN_SEED, loan_account.seed_domain.as_ref()],
+        bump = loan_account.bump,
+        constraint = loan_account.borrower == borrower.key(),
+        constraint = loan_account.status == LoanStatus::SetupPending,
+        constraint = loan_account.loan_type == LoanType::P2P,
+        constraint = loan_account.lender_source == used_nonce.key()
+    )]
+    pub loan_account: Account<'info, LoanAccount>,
+
+    // Initializing the nonce PDA fails if the offer was already taken or invalidated.
+    #[account(
+        init,
+        seeds = [NONCE_SEED, terms.lender.as_ref(), terms.nonce.to_le_bytes().as_ref()],
+        bump,
+        payer = borrower,
+        space = 8 + std::mem::size_of::<UsedNonce>()
+    )]
+    pub used_nonce: Account<'info, UsedNonce>,
+
+    /// CHECK: Lender profile PDA; may not exist yet
+    #[account(seeds = [LENDER_SEED, terms.lender.as_ref()], bump)]
+    pub lender_profile: AccountInfo<'info>,
+
+    /// CHECK: Program delegate approved by the lender on lender_usdc
+    #[account(seeds = [DELEGATE_SEED], bump)]
+    pub delegate: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = lender_usdc.owner == terms.lender,
+        constraint = lender_usdc.mint == terms.mint
+    )]
+    pub lender_usdc: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub borrower_usdc: Account<'info, TokenAccount>,
+
+    /// CHECK: Instructions sysvar, read to find the ed25519 verify instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub clock: Sysvar<'info, Clock>,
+}
+
+pub fn take_signed_offer(ctx: Context<TakeSignedOffer>, terms: SignedOfferTerms) -> Result<()> {
+    let now = ctx.accounts.clock.unix_timestamp;
+
+    require!(now < terms.expiry, ErrorCode::OfferExpired);
+    require!(terms.principal > 0 && terms.duration_seconds > 0, ErrorCode::InvalidOfferTerms);
+    require!(
+        terms.nonce >= LenderProfile::min_nonce_of(&ctx.accounts.lender_profile)?,
+        ErrorCode::NonceInvalidated
+    );
+    if let Some(domain) = terms.domain {
+        require_keys_eq!(domain, ctx.accounts.loan_account.domain_registry, ErrorCode::OfferDomainMismatch);
+    }
+
+    verify_ed25519_ix(&ctx.accounts.instructions, &terms.lender, &terms.message()?)?;
+
+    let used_nonce = &mut ctx.accounts.used_nonce;
+    used_nonce.lender = terms.lender;
+    used_nonce.nonce = terms.nonce;
+    used_nonce.bump = ctx.bumps.used_nonce;
+
+    // Update Loan
+    let loan = &mut ctx.accounts.loan_account;
+    loan.principal_amount = terms.principal;
+    loan.apr_bps = terms.apr_bps;
+    loan.start_ts = now;
+    loan.due_ts = now.checked_add(terms.duration_seconds).ok_or(ErrorCode::MathOverflow)?;
+    loan.duration_seconds = terms.duration_seconds;
+    loan.status = LoanStatus::Active;
+    loan.lender_source = terms.lender;
+    loan.creditor = terms.lender;
+    loan.mint = terms.mint;
+
+    // Pull principal from the lender via the program delegate
+    let seeds = &[DELEGATE_SEED, &[ctx.bumps.delegate]];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.lender_usdc.to_account_info(),
+        to: ctx.accounts.borrower_usdc.to_account_info(),
+        authority: ctx.accounts.delegate.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, terms.principal)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct InvalidateNonce<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [NONCE_SEED, lender.key().as_ref(), nonce.to_le_bytes().as_ref()],
+        bump,
+        payer = lender,
+        space = 8 + std::mem::size_of::<UsedNonce>()
+    )]
+    pub used_nonce: Account<'info, UsedNonce>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Burns a signed-offer nonce so a quote that was handed out can no longer be taken.
+pub fn invalidate_nonce(ctx: Context<InvalidateNonce>, nonce: u64) -> Result<()> {
+    let used_nonce = &mut ctx.accounts.used_nonce;
+    used_nonce.lender = ctx.accounts.lender.key();
+    used_nonce.nonce = nonce;
+    used_nonce.bump = ctx.bumps.used_nonce;
+    Ok(())
+}
+
+/// Checks that the instruction immediately before this one is an ed25519
+/// program instruction verifying `message` signed by `signer`, with all
+/// signature data embedded in that instruction.
+fn verify_ed25519_ix(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = ix_sysvar::load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::InvalidOfferSignature);
+    let ix = ix_sysvar::load_instruction_at_checked((current - 1) as usize, instructions)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, ErrorCode::InvalidOfferSignature);
+    check_ed25519_data(&ix.data, signer, message)
+}
+
+/// Checks the data of an ed25519 program instruction: a single signature of
+/// `message` by `signer`, with the public key and message read from the
+/// instruction itself.
+fn check_ed25519_data(data: &[u8], signer: &Pubkey, message: &[u8]) -> Result<()> {
+    // Header: num_signatures (u8), padding (u8), then 7 little-endian u16 offsets.
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidOfferSignature);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_ix_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_ix_index = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_ix_index = read_u16(14);
+
+    require!(
+        signature_ix_index == u16::MAX && public_key_ix_index == u16::MAX && message_ix_index == u16::MAX,
+        ErrorCode::InvalidOfferSignature
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidOfferSignature)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(ErrorCode::InvalidOfferSignature)?;
+
+    require!(public_key == signer.as_ref(), ErrorCode::InvalidOfferSignature);
+    require!(signed_message == message, ErrorCode::InvalidOfferSignature);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    /// ed25519 program data with the public key, a dummy signature and the
+    /// message embedded after the 16-byte header.
+    fn ed25519_data(signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset,
+            u16::MAX,
+            public_key_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn accepts_embedded_signature() {
+        let data = ed25519_data(&key(1), b"terms");
+        assert!(check_ed25519_data(&data, &key(1), b"terms").is_ok());
+    }
+
+    #[test]
+    fn rejects_other_signer() {
+        let data = ed25519_data(&key(1), b"terms");
+        assert!(check_ed25519_data(&data, &key(2), b"terms").is_err());
+    }
+
+    #[test]
+    fn rejects_other_message() {
+        let data = ed25519_data(&key(1), b"terms");
+        assert!(check_ed25519_data(&data, &key(1), b"other").is_err());
+    }
+
+    #[test]
+    fn rejects_multiple_signatures() {
+        let mut data = ed25519_data(&key(1), b"terms");
+        data[0] = 2;
+        assert!(check_ed25519_data(&data, &key(1), b"terms").is_err());
+    }
+
+    #[test]
+    fn rejects_data_from_other_instructions() {
+        // signature, public key and message instruction indexes
+        for at in [4, 8, 14] {
+            let mut data = ed25519_data(&key(1), b"terms");
+            data[at..at + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert!(check_ed25519_data(&data, &key(1), b"terms").is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_offsets() {
+        let mut data = ed25519_data(&key(1), b"terms");
+        data[12..14].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(check_ed25519_data(&data, &key(1), b"terms").is_err());
+
+        let mut data = ed25519_data(&key(1), b"terms");
+        let past_end = (data.len() - 8) as u16;
+        data[6..8].copy_from_slice(&past_end.to_le_bytes());
+        assert!(check_ed25519_data(&data, &key(1), b"terms").is_err());
+    }
+
+    #[test]
+    fn rejects_short_data() {
+        assert!(check_ed25519_data(&[1, 0, 0, 0], &key(1), b"terms").is_err());
+    }
+
+    #[test]
+    fn message_is_bound_to_the_program() {
+        let terms = SignedOfferTerms {
+            lender: key(1),
+            mint: key(2),
+            principal: 1_000,
+            apr_bps: 1000,
+            duration_seconds: 86_400,
+            nonce: 7,
+            expiry: 0,
+            domain: None,
+        };
+        let message = terms.message().unwrap();
+        let (prefix, rest) = message.split_at(SIGNED_OFFER_PREFIX.len());
+        assert_eq!(prefix, SIGNED_OFFER_PREFIX);
+        assert_eq!(&rest[..32], crate::ID.as_ref());
+        assert_eq!(&rest[32..], terms.try_to_vec().unwrap().as_slice());
+    }
+}
@@ -5,12 +5,20 @@ pub mod instructions;
 pub mod error;
 pub mod events;
 pub mod constants;
+pub mod admission;
+pub mod sns;
 
 use instructions::admin::*;
 use instructions::lp::*;
 use instructions::p2p::*;
 use instructions::borrow::*;
 use instructions::liquidation::*;
+use instructions::request::*;
+use instructions::signed_offer::*;
+use instructions::claim::*;
+use instructions::buyout::*;
+use instructions::record::*;
+use instructions::basket::*;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -26,6 +34,14 @@ pub mod solname_credit {
         instructions::admin::init_pool(ctx)
     }
 
+    pub fn update_global_params(ctx: Context<UpdateGlobalParams>, params: GlobalParams) -> Result<()> {
+        instructions::admin::update_global_params(ctx, params)
+    }
+
+    pub fn set_subdomain_registrar(ctx: Context<SetSubdomainRegistrar>, program_id: Pubkey) -> Result<()> {
+        instructions::admin::set_subdomain_registrar(ctx, program_id)
+    }
+
     // pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, amount: u64) -> Result<()> {
     //     instructions::lp::deposit_liquidity(ctx, amount)
     // }
@@ -34,41 +50,134 @@ pub mod solname_credit {
         instructions::lp::withdraw_liquidity(ctx, shares)
     }
 
-    pub fn create_offer(
-        ctx: Context<CreateOffer>,
-        nonce: u64,
-        principal: u64,
-        apr_bps: u16,
-        duration_seconds: i64,
-        offer_expiry: i64
-    ) -> Result<()> {
-        instructions::p2p::create_offer(ctx, nonce, principal, apr_bps, duration_seconds, offer_expiry)
+    pub fn create_offer(ctx: Context<CreateOffer>, nonce: u64, params: OfferParams) -> Result<()> {
+        instructions::p2p::create_offer(ctx, nonce, params)
     }
 
     pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
         instructions::p2p::cancel_offer(ctx)
     }
 
-    pub fn setup_collateral(
-        ctx: Context<SetupCollateral>,
+    pub fn call_loan(ctx: Context<CallLoan>) -> Result<()> {
+        instructions::p2p::call_loan(ctx)
+    }
+
+    pub fn cancel_offers<'info>(ctx: Context<'_, '_, 'info, 'info, CancelOffers<'info>>) -> Result<()> {
+        instructions::p2p::cancel_offers(ctx)
+    }
+
+    pub fn set_min_nonce(ctx: Context<SetMinNonce>, min_nonce: u64) -> Result<()> {
+        instructions::p2p::set_min_nonce(ctx, min_nonce)
+    }
+
+    pub fn take_signed_offer(ctx: Context<TakeSignedOffer>, terms: SignedOfferTerms) -> Result<()> {
+        instructions::signed_offer::take_signed_offer(ctx, terms)
+    }
+
+    pub fn invalidate_nonce(ctx: Context<InvalidateNonce>, nonce: u64) -> Result<()> {
+        instructions::signed_offer::invalidate_nonce(ctx, nonce)
+    }
+
+    pub fn mint_loan_claim(ctx: Context<MintLoanClaim>) -> Result<()> {
+        instructions::claim::mint_loan_claim(ctx)
+    }
+
+    pub fn list_loan_claim(ctx: Context<ListLoanClaim>, price: u64) -> Result<()> {
+        instructions::claim::list_loan_claim(ctx, price)
+    }
+
+    pub fn buy_loan_claim(ctx: Context<BuyLoanClaim>) -> Result<()> {
+        instructions::claim::buy_loan_claim(ctx)
+    }
+
+    pub fn delist_loan_claim(ctx: Context<DelistLoanClaim>) -> Result<()> {
+        instructions::claim::delist_loan_claim(ctx)
+    }
+
+    pub fn pool_buyout(ctx: Context<PoolBuyout>) -> Result<()> {
+        instructions::buyout::pool_buyout(ctx)
+    }
+
+    pub fn setup_collateral<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetupCollateral<'info>>,
         mode: LoanTypeInput,
-        offer_id: Option<Pubkey>
+        offer_id: Option<Pubkey>,
+        payout: Pubkey
     ) -> Result<()> {
-        instructions::borrow::setup_collateral(ctx, mode, offer_id)
+        instructions::borrow::setup_collateral(ctx, mode, offer_id, payout)
+    }
+
+    pub fn add_basket_domain(ctx: Context<AddBasketDomain>) -> Result<()> {
+        instructions::basket::add_basket_domain(ctx)
+    }
+
+    pub fn swap_collateral<'info>(ctx: Context<'_, '_, 'info, 'info, SwapCollateral<'info>>, payout: Pubkey) -> Result<()> {
+        instructions::basket::swap_collateral(ctx, payout)
+    }
+
+    pub fn cancel_setup<'info>(ctx: Context<'_, '_, 'info, 'info, CancelSetup<'info>>) -> Result<()> {
+        instructions::borrow::cancel_setup(ctx)
     }
 
     pub fn verify_and_withdraw_pool(ctx: Context<VerifyAndWithdrawPool>) -> Result<()> {
         instructions::borrow::verify_and_withdraw_pool(ctx)
     }
 
-    pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>) -> Result<()> {
-        instructions::borrow::verify_and_withdraw_p2p(ctx)
+    pub fn verify_and_withdraw_p2p(ctx: Context<VerifyAndWithdrawP2P>, amount: u64) -> Result<()> {
+        instructions::borrow::verify_and_withdraw_p2p(ctx, amount)
     }
 
-    pub fn repay(ctx: Context<Repay>) -> Result<()> {
+    pub fn repay<'info>(ctx: Context<'_, '_, 'info, 'info, Repay<'info>>) -> Result<()> {
         instructions::borrow::repay(ctx)
     }
 
+    pub fn repay_for<'info>(ctx: Context<'_, '_, 'info, 'info, RepayFor<'info>>) -> Result<()> {
+        instructions::borrow::repay_for(ctx)
+    }
+
+    pub fn set_repay_delegate(ctx: Context<SetRepayDelegate>, delegate: Option<Pubkey>) -> Result<()> {
+        instructions::borrow::set_repay_delegate(ctx, delegate)
+    }
+
+    pub fn repay_partial(ctx: Context<RepayPartial>, amount: u64) -> Result<()> {
+        instructions::borrow::repay_partial(ctx, amount)
+    }
+
+    pub fn pay_installment<'info>(ctx: Context<'_, '_, 'info, 'info, Repay<'info>>) -> Result<()> {
+        instructions::borrow::pay_installment(ctx)
+    }
+
+    pub fn update_record(ctx: Context<UpdateRecord>, kind: RecordKind, offset: u32, data: Vec<u8>) -> Result<()> {
+        instructions::record::update_record(ctx, kind, offset, data)
+    }
+
+    pub fn refinance(ctx: Context<Refinance>, amount: u64) -> Result<()> {
+        instructions::borrow::refinance(ctx, amount)
+    }
+
+    pub fn rollover(ctx: Context<Rollover>) -> Result<()> {
+        instructions::borrow::rollover(ctx)
+    }
+
+    pub fn create_request<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateRequest<'info>>,
+        principal: u64,
+        max_apr_bps: u16,
+        duration_seconds: i64,
+        expiry: i64,
+        payout: Pubkey
+    ) -> Result<()> {
+        instructions::request::create_request(ctx, principal, max_apr_bps, duration_seconds, expiry, payout)
+    }
+
+    pub fn fill_request(ctx: Context<FillRequest>, apr_bps: u16) -> Result<()> {
+        instructions::request::fill_request(ctx, apr_bps)
+    }
+
+    pub fn cancel_request<'info>(ctx: Context<'_, '_, 'info, 'info, CancelRequest<'info>>) -> Result<()> {
+        instructions::request::cancel_request(ctx)
+    }
+
     pub fn enter_grace(ctx: Context<EnterGrace>) -> Result<()> {
         instructions::liquidation::enter_grace(ctx)
     }
@@ -77,6 +186,10 @@ pub mod solname_credit {
         instructions::liquidation::start_auction(ctx)
     }
 
+    pub fn start_lot_auction(ctx: Context<StartLotAuction>, domain: Pubkey) -> Result<()> {
+        instructions::liquidation::start_lot_auction(ctx, domain)
+    }
+
     pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
         instructions::liquidation::place_bid(ctx, amount)
     }
@@ -85,9 +198,13 @@ pub mod solname_credit {
         instructions::liquidation::buy_it_now(ctx)
     }
 
-    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+    pub fn settle_auction<'info>(ctx: Context<'_, '_, 'info, 'info, SettleAuction<'info>>) -> Result<()> {
         instructions::liquidation::settle_auction(ctx)
     }
+
+    pub fn cleanup_record(ctx: Context<CleanupRecord>) -> Result<()> {
+        instructions::liquidation::cleanup_record(ctx)
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
@@ -96,4 +213,30 @@ pub struct GlobalParams {
     pub grace_period_seconds: i64,
     pub min_bid_increment_bps: u16,
     pub auction_duration_seconds: i64,
+    pub max_pool_rollovers: u8,
+    pub pool_min_interest_seconds: i64,
+    pub pool_origination_interest_bps: u16,
+    pub pool_buyout_max_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct OfferParams {
+    pub total_budget: u64,
+    pub max_per_loan: u64,
+    pub apr_bps: u16,
+    pub duration_seconds: i64,
+    pub offer_expiry: i64,
+    pub max_rollovers: u8,
+    /// Zero for a bullet loan; otherwise `installment_period * installment_count`
+    /// must equal `duration_seconds`.
+    pub installment_period: i64,
+    pub installment_count: u16,
+    /// Callable loans have no fixed maturity; the lender may call them with
+    /// `notice_period_seconds` notice.
+    pub callable: bool,
+    pub notice_period_seconds: i64,
+    /// Interest owed even on early repayment: the larger of
+    /// `min_interest_seconds` of interest and `origination_interest_bps` of principal.
+    pub min_interest_seconds: i64,
+    pub origination_interest_bps: u16,
 }
@@ -0,0 +1,377 @@
+//! Minimal client for the SPL name service program: instruction builders,
+//! invoke helpers, name account derivation and registry header parsing.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hashv,
+    instruction::Instruction,
+    program::invoke_signed,
+    system_program,
+};
+use crate::constants::NAME_SERVICE_ID;
+use crate::error::ErrorCode;
+
+/// Prefix hashed in front of every name.
+pub const HASH_PREFIX: &str = "SPL Name Service";
+
+/// Length of the `NameRecordHeader` at the start of every registry account.
+pub const NAME_RECORD_HEADER_LEN: usize = 96;
+
+/// Name service instruction tags.
+const TAG_UPDATE: u8 = 1;
+const TAG_TRANSFER: u8 = 2;
+const TAG_DELETE: u8 = 3;
+const TAG_REALLOC: u8 = 4;
+
+/// Fixed header of an SNS name registry account.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NameRecordHeader {
+    pub parent_name: Pubkey,
+    pub owner: Pubkey,
+    pub class: Pubkey,
+}
+
+impl NameRecordHeader {
+    pub fn unpack(data: &[u8]) -> Result<Self> {
+        require!(data.len() >= NAME_RECORD_HEADER_LEN, ErrorCode::InvalidDomainOwner);
+        let key_at = |at: usize| Pubkey::new_from_array(data[at..at + 32].try_into().unwrap());
+        Ok(Self {
+            parent_name: key_at(0),
+            owner: key_at(32),
+            class: key_at(64),
+        })
+    }
+
+    pub fn load(name_account: &AccountInfo) -> Result<Self> {
+        Self::unpack(&name_account.try_borrow_data()?)
+    }
+}
+
+/// Address stored in a V1 SOL record, or `None` if the record does not exist.
+pub fn sol_record_address(record: &AccountInfo) -> Result<Option<Pubkey>> {
+    if record.data_is_empty() || *record.owner != NAME_SERVICE_ID {
+        return Ok(None);
+    }
+    let data = record.try_borrow_data()?;
+    let address = data
+        .get(NAME_RECORD_HEADER_LEN..NAME_RECORD_HEADER_LEN + 32)
+        .ok_or(ErrorCode::SolRecordMismatch)?;
+    Ok(Some(Pubkey::new_from_array(address.try_into().unwrap())))
+}
+
+/// Hash of a name account's contents with the owner field left out, so it
+/// survives transfers in and out of escrow. Zero if the account does not exist.
+pub fn content_hash(name_account: &AccountInfo) -> Result<[u8; 32]> {
+    if name_account.data_is_empty() || *name_account.owner != NAME_SERVICE_ID {
+        return Ok([0; 32]);
+    }
+    let data = name_account.try_borrow_data()?;
+    require!(data.len() >= NAME_RECORD_HEADER_LEN, ErrorCode::InvalidDomainOwner);
+    Ok(hashv(&[&data[..32], &data[64..]]).to_bytes())
+}
+
+pub fn hashed_name(name: &str) -> [u8; 32] {
+    hashv(&[HASH_PREFIX.as_bytes(), name.as_bytes()]).to_bytes()
+}
+
+/// Address of a V1 record (e.g. `"SOL"`) of `domain`.
+pub fn record_v1_key(domain: &Pubkey, record: &str) -> Pubkey {
+    name_account_key(&hashed_name(&format!("\x01{}", record)), None, Some(domain))
+}
+
+/// Address of the name account for `hashed_name` under `class` and `parent`
+/// (default key when absent).
+pub fn name_account_key(hashed_name: &[u8; 32], class: Option<&Pubkey>, parent: Option<&Pubkey>) -> Pubkey {
+    let default = Pubkey::default();
+    let class = class.unwrap_or(&default);
+    let parent = parent.unwrap_or(&default);
+    Pubkey::find_program_address(&[hashed_name, class.as_ref(), parent.as_ref()], &NAME_SERVICE_ID).0
+}
+
+pub fn transfer_ix(name_account: &Pubkey, owner: &Pubkey, new_owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: NAME_SERVICE_ID,
+        accounts: vec![
+            AccountMeta::new(*name_account, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: [&[TAG_TRANSFER][..], new_owner.as_ref()].concat(),
+    }
+}
+
+/// Transfer signed by the owner of `parent_name` rather than the account
+/// owner. The default-class slot is the system program id.
+pub fn transfer_by_parent_ix(name_account: &Pubkey, parent_owner: &Pubkey, parent_name: &Pubkey, new_owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: NAME_SERVICE_ID,
+        accounts: vec![
+            AccountMeta::new(*name_account, false),
+            AccountMeta::new_readonly(*parent_owner, true),
+            AccountMeta::new_readonly(Pubkey::default(), false),
+            AccountMeta::new_readonly(*parent_name, false),
+        ],
+        data: [&[TAG_TRANSFER][..], new_owner.as_ref()].concat(),
+    }
+}
+
+/// Writes `data` at `offset` into the name account's data (after the header).
+pub fn update_ix(name_account: &Pubkey, signer: &Pubkey, offset: u32, data: &[u8]) -> Instruction {
+    Instruction {
+        program_id: NAME_SERVICE_ID,
+        accounts: vec![
+            AccountMeta::new(*name_account, false),
+            AccountMeta::new_readonly(*signer, true),
+        ],
+        data: [
+            &[TAG_UPDATE][..],
+            &offset.to_le_bytes(),
+            &(data.len() as u32).to_le_bytes(),
+            data,
+        ]
+        .concat(),
+    }
+}
+
+pub fn delete_ix(name_account: &Pubkey, owner: &Pubkey, refund_target: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: NAME_SERVICE_ID,
+        accounts: vec![
+            AccountMeta::new(*name_account, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*refund_target, false),
+        ],
+        data: vec![TAG_DELETE],
+    }
+}
+
+pub fn realloc_ix(payer: &Pubkey, name_account: &Pubkey, owner: &Pubkey, space: u32) -> Instruction {
+    Instruction {
+        program_id: NAME_SERVICE_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*name_account, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: [&[TAG_REALLOC][..], &space.to_le_bytes()].concat(),
+    }
+}
+
+/// Transfers `name_account` from `owner` to `new_owner`. Pass the escrow
+/// seeds in `signer_seeds` when the owner is a PDA.
+pub fn transfer<'info>(
+    name_service: &AccountInfo<'info>,
+    name_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    new_owner: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = transfer_ix(name_account.key, owner.key, new_owner);
+    invoke_signed(&ix, &[name_account.clone(), owner.clone(), name_service.clone()], signer_seeds)?;
+    Ok(())
+}
+
+pub fn transfer_by_parent<'info>(
+    name_service: &AccountInfo<'info>,
+    name_account: &AccountInfo<'info>,
+    parent_owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    parent_name: &AccountInfo<'info>,
+    new_owner: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = transfer_by_parent_ix(name_account.key, parent_owner.key, parent_name.key, new_owner);
+    invoke_signed(
+        &ix,
+        &[
+            name_account.clone(),
+            parent_owner.clone(),
+            system_program.clone(),
+            parent_name.clone(),
+            name_service.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Takes `record` (a child of `parent_name`) from whoever owns it and deletes
+/// it, refunding its rent to `refund_target`. `parent_owner` must own
+/// `parent_name`. No-op if the record does not exist.
+#[allow(clippy::too_many_arguments)]
+pub fn delete_child_record<'info>(
+    name_service: &AccountInfo<'info>,
+    record: &AccountInfo<'info>,
+    parent_owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    parent_name: &AccountInfo<'info>,
+    refund_target: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if record.data_is_empty() || *record.owner != NAME_SERVICE_ID {
+        return Ok(());
+    }
+    if NameRecordHeader::load(record)?.owner != *parent_owner.key {
+        transfer_by_parent(name_service, record, parent_owner, system_program, parent_name, parent_owner.key, signer_seeds)?;
+    }
+    delete(name_service, record, parent_owner, refund_target, signer_seeds)
+}
+
+pub fn update<'info>(
+    name_service: &AccountInfo<'info>,
+    name_account: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    offset: u32,
+    data: &[u8],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = update_ix(name_account.key, signer.key, offset, data);
+    invoke_signed(&ix, &[name_account.clone(), signer.clone(), name_service.clone()], signer_seeds)?;
+    Ok(())
+}
+
+pub fn delete<'info>(
+    name_service: &AccountInfo<'info>,
+    name_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    refund_target: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = delete_ix(name_account.key, owner.key, refund_target.key);
+    invoke_signed(
+        &ix,
+        &[name_account.clone(), owner.clone(), refund_target.clone(), name_service.clone()],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+pub fn realloc<'info>(
+    name_service: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    name_account: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    space: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let ix = realloc_ix(payer.key, name_account.key, owner.key, space);
+    invoke_signed(
+        &ix,
+        &[system_program.clone(), payer.clone(), name_account.clone(), owner.clone(), name_service.clone()],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::SOL_TLD_ROOT;
+    use std::str::FromStr;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    #[test]
+    fn unpacks_header() {
+        let mut data = vec![0u8; NAME_RECORD_HEADER_LEN + 8];
+        data[0..32].fill(1);
+        data[32..64].fill(2);
+        data[64..96].fill(3);
+
+        let header = NameRecordHeader::unpack(&data).unwrap();
+        assert_eq!(header.parent_name, key(1));
+        assert_eq!(header.owner, key(2));
+        assert_eq!(header.class, key(3));
+    }
+
+    #[test]
+    fn rejects_short_header() {
+        assert!(NameRecordHeader::unpack(&[0u8; NAME_RECORD_HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn hashes_name_with_prefix() {
+        let expected: [u8; 32] = [
+            0x8e, 0xe2, 0xd2, 0x5c, 0x3d, 0x2b, 0x2a, 0x83, 0xa1, 0xfc, 0x20, 0x9b, 0x90, 0x37, 0x7a, 0xed,
+            0x03, 0xdc, 0x25, 0x39, 0xe8, 0xe2, 0x38, 0x35, 0x5e, 0xdd, 0xa8, 0xd1, 0xb2, 0xed, 0xab, 0x98,
+        ];
+        assert_eq!(hashed_name("bonfida"), expected);
+    }
+
+    #[test]
+    fn derives_domain_key() {
+        let domain = name_account_key(&hashed_name("bonfida"), None, Some(&SOL_TLD_ROOT));
+        assert_eq!(domain, Pubkey::from_str("Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb").unwrap());
+    }
+
+    #[test]
+    fn builds_transfer() {
+        let ix = transfer_ix(&key(1), &key(2), &key(3));
+        assert_eq!(ix.program_id, NAME_SERVICE_ID);
+        assert_eq!(ix.data.len(), 33);
+        assert_eq!(ix.data[0], 2);
+        assert_eq!(&ix.data[1..], key(3).as_ref());
+        assert_eq!(ix.accounts, vec![AccountMeta::new(key(1), false), AccountMeta::new_readonly(key(2), true)]);
+    }
+
+    #[test]
+    fn builds_transfer_by_parent() {
+        let ix = transfer_by_parent_ix(&key(1), &key(2), &key(4), &key(3));
+        assert_eq!(ix.data[0], 2);
+        assert_eq!(&ix.data[1..], key(3).as_ref());
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(key(1), false),
+                AccountMeta::new_readonly(key(2), true),
+                AccountMeta::new_readonly(Pubkey::default(), false),
+                AccountMeta::new_readonly(key(4), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn derives_record_v1_key() {
+        let domain = key(7);
+        assert_eq!(record_v1_key(&domain, "SOL"), name_account_key(&hashed_name("\x01SOL"), None, Some(&domain)));
+    }
+
+    #[test]
+    fn builds_update() {
+        let ix = update_ix(&key(1), &key(2), 0x0102, &[0xaa, 0xbb]);
+        assert_eq!(ix.data, vec![1, 0x02, 0x01, 0, 0, 2, 0, 0, 0, 0xaa, 0xbb]);
+        assert_eq!(ix.accounts, vec![AccountMeta::new(key(1), false), AccountMeta::new_readonly(key(2), true)]);
+    }
+
+    #[test]
+    fn builds_delete() {
+        let ix = delete_ix(&key(1), &key(2), &key(3));
+        assert_eq!(ix.data, vec![3]);
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(key(1), false),
+                AccountMeta::new_readonly(key(2), true),
+                AccountMeta::new(key(3), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_realloc() {
+        let ix = realloc_ix(&key(1), &key(2), &key(3), 1000);
+        assert_eq!(ix.data, vec![4, 0xe8, 0x03, 0, 0]);
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(key(1), true),
+                AccountMeta::new(key(2), false),
+                AccountMeta::new_readonly(key(3), true),
+            ]
+        );
+    }
+}
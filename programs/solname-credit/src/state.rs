@@ -1,4 +1,6 @@
 use anchor_lang::prelude::*;
+use crate::constants::{BPS_DENOMINATOR, SECONDS_PER_YEAR, OPEN_ENDED_DUE_TS, MAX_SNAPSHOT_RECORDS, MAX_BASKET_DOMAINS};
+use crate::error::ErrorCode;
 
 #[account]
 pub struct GlobalState {
@@ -8,6 +10,14 @@ pub struct GlobalState {
     pub grace_period_seconds: i64,
     pub min_bid_increment_bps: u16,
     pub auction_duration_seconds: i64,
+    pub max_pool_rollovers: u8,
+    /// Minimum-interest terms applied to pool loans.
+    pub pool_min_interest_seconds: i64,
+    pub pool_origination_interest_bps: u16,
+    /// Largest P2P loan (par plus accrued interest) the pool will buy out.
+    pub pool_buyout_max_amount: u64,
+    /// Subdomain registrar program whose registrar PDA disqualifies a domain.
+    pub subdomain_registrar_program: Pubkey,
 }
 
 #[account]
@@ -16,9 +26,47 @@ pub struct PoolState {
     pub vault_authority: Pubkey,
     pub total_shares: u64,
     pub total_assets: u64,
+    pub total_principal_outstanding: u64,
+    pub total_interest_collected: u64,
     pub bump: u8,
 }
 
+impl PoolState {
+    /// Books principal lent out of the vault.
+    pub fn record_disbursement(&mut self, principal: u64) -> Result<()> {
+        self.total_principal_outstanding = self
+            .total_principal_outstanding
+            .checked_add(principal)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Books a payment received into the vault. Interest accrues to LPs
+    /// through `total_assets`; returned principal was already counted there.
+    pub fn record_repayment(&mut self, principal: u64, interest: u64) -> Result<()> {
+        self.total_principal_outstanding = self
+            .total_principal_outstanding
+            .checked_sub(principal)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_interest_collected = self
+            .total_interest_collected
+            .checked_add(interest)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_assets = self.total_assets.checked_add(interest).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Writes off principal a settled liquidation did not recover.
+    pub fn record_loss(&mut self, principal: u64) -> Result<()> {
+        self.total_principal_outstanding = self
+            .total_principal_outstanding
+            .checked_sub(principal)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_assets = self.total_assets.saturating_sub(principal);
+        Ok(())
+    }
+}
+
 #[account]
 pub struct LpPosition {
     pub owner: Pubkey,
@@ -29,34 +77,447 @@ pub struct LpPosition {
 #[account]
 pub struct LoanAccount {
     pub borrower: Pubkey,
+    /// Primary escrowed domain; can change through `swap_collateral`.
     pub domain_registry: Pubkey,
+    /// Domain the loan PDA was derived from at setup; never changes.
+    pub seed_domain: Pubkey,
     pub escrow_pda: Pubkey,
     pub principal_amount: u64,
     pub repaid_amount: u64,
+    /// Interest accrued before `last_update_ts` that a partial payment did not cover.
+    pub unpaid_interest: u64,
+    /// Interest owed even on early repayment; reduced as interest is paid.
+    pub min_interest: u64,
     pub apr_bps: u16,
     pub start_ts: i64,
     pub due_ts: i64,
+    pub duration_seconds: i64,
+    /// Installment schedule (zero count = single bullet payment at `due_ts`).
+    pub installment_period: i64,
+    pub installment_count: u16,
+    pub installment_amount: u64,
+    /// Paid toward the schedule since origination.
+    pub schedule_paid: u64,
+    pub rollover_count: u8,
+    /// Rollovers pre-agreed by the lender (from the offer) that need no
+    /// lender signature. Pool loans use `GlobalState::max_pool_rollovers`.
+    pub max_rollovers: u8,
+    /// Callable loans have no fixed maturity until the lender calls them.
+    pub callable: bool,
+    pub notice_period_seconds: i64,
     pub grace_end_ts: i64,
     pub last_update_ts: i64,
     pub status: LoanStatus,
     pub loan_type: LoanType,
     pub lender_source: Pubkey,
+    /// Lender funding a P2P loan directly (e.g. a filled `LoanRequest`).
+    /// Repayments go to this wallet; unset for offer-backed loans, which
+    /// are repaid into the offer vault.
+    pub creditor: Pubkey,
+    /// Token mint the loan was funded in; every payment must be made in it.
+    pub mint: Pubkey,
+    /// Mint of the one-of-one claim token; once set, its holder is the creditor.
+    pub claim_mint: Pubkey,
+    /// Claim mints issued so far. Seeds the next claim mint, so a fresh claim
+    /// can be minted after refinance or a buyout retired the previous one.
+    pub claim_generation: u8,
+    /// Address the domain's SOL record should point to while the borrower
+    /// holds the domain; restored on repay and scrubbed at settlement.
     pub record_payout: Pubkey,
+    /// True once the SOL record was seen or written holding `record_payout`.
+    pub record_confirmed: bool,
+    /// Domains whose SOL record settlement could not delete, one bit per
+    /// domain (see `domain_bit`).
+    pub record_cleanup_mask: u8,
+    /// Domain and record contents at setup, checked before repay returns the domain.
+    pub record_snapshot: RecordSnapshot,
+    /// Borrower-authorized payer who may choose the domain recipient in `repay_for`.
+    pub repay_delegate: Pubkey,
+    /// Extra domains escrowed alongside `domain_registry` in a basket loan.
+    pub basket: [Pubkey; MAX_BASKET_DOMAINS],
+    pub basket_count: u8,
+    /// Per-domain auctions opened and settled when the collateral is sold
+    /// one domain at a time; zero while it is auctioned as a single lot.
+    pub auction_lots: u8,
+    pub lots_settled: u8,
+    /// Domains auctioned or returned one at a time, one bit per domain (see
+    /// `domain_bit`).
+    pub auction_lot_mask: u8,
+    /// Auction proceeds paid to the lender toward the amount due.
+    pub auction_proceeds: u64,
     pub bump: u8,
 }
 
+impl LoanAccount {
+    /// Basket domains, excluding the primary `domain_registry`.
+    pub fn basket(&self) -> &[Pubkey] {
+        &self.basket[..self.basket_count as usize]
+    }
+
+    /// Number of domains securing the loan.
+    pub fn domain_count(&self) -> u8 {
+        self.basket_count + 1
+    }
+
+    pub fn holds_domain(&self, domain: &Pubkey) -> bool {
+        self.domain_registry == *domain || self.basket().contains(domain)
+    }
+
+    /// Bit of `domain` in per-domain masks: bit 0 for `domain_registry`,
+    /// bit `i + 1` for `basket[i]`.
+    pub fn domain_bit(&self, domain: &Pubkey) -> Option<u8> {
+        if self.domain_registry == *domain {
+            return Some(1);
+        }
+        self.basket().iter().position(|d| d == domain).map(|at| 1 << (at + 1))
+    }
+
+    /// True when settlement left `domain`'s SOL record behind.
+    pub fn needs_record_cleanup(&self, domain: &Pubkey) -> bool {
+        self.domain_bit(domain).is_some_and(|bit| self.record_cleanup_mask & bit != 0)
+    }
+
+    /// Replaces `old`, the primary or a basket domain, with `new`.
+    pub fn replace_domain(&mut self, old: &Pubkey, new: Pubkey) -> Result<()> {
+        require!(!self.holds_domain(&new), ErrorCode::BasketMismatch);
+        if self.domain_registry == *old {
+            self.domain_registry = new;
+            return Ok(());
+        }
+        let at = self.basket().iter().position(|d| d == old).ok_or(ErrorCode::BasketMismatch)?;
+        self.basket[at] = new;
+        Ok(())
+    }
+
+    pub fn add_to_basket(&mut self, domain: Pubkey) -> Result<()> {
+        let at = self.basket_count as usize;
+        require!(at < MAX_BASKET_DOMAINS, ErrorCode::BasketFull);
+        require!(!self.holds_domain(&domain), ErrorCode::BasketMismatch);
+        self.basket[at] = domain;
+        self.basket_count += 1;
+        Ok(())
+    }
+
+    /// Starts the loan on `offer`'s terms at `now`.
+    pub fn apply_offer_terms(&mut self, offer: &OfferAccount, now: i64) -> Result<()> {
+        self.apr_bps = offer.apr_bps;
+        self.start_ts = now;
+        self.last_update_ts = now;
+        self.duration_seconds = offer.duration_seconds;
+        self.due_ts = if offer.callable {
+            OPEN_ENDED_DUE_TS
+        } else {
+            now.checked_add(offer.duration_seconds).ok_or(ErrorCode::MathOverflow)?
+        };
+        self.callable = offer.callable;
+        self.notice_period_seconds = offer.notice_period_seconds;
+        self.rollover_count = 0;
+        self.max_rollovers = offer.max_rollovers;
+        self.set_min_interest(offer.min_interest_seconds, offer.origination_interest_bps)?;
+        self.start_schedule(offer.installment_period, offer.installment_count)
+    }
+
+    /// Fixes the interest always owed on the current principal: the larger of
+    /// `min_seconds` of interest at `apr_bps` and a flat `origination_bps`.
+    pub fn set_min_interest(&mut self, min_seconds: i64, origination_bps: u16) -> Result<()> {
+        let principal = self.principal_amount as u128;
+        let period = principal
+            .checked_mul(self.apr_bps as u128)
+            .and_then(|v| v.checked_mul(min_seconds.max(0) as u128))
+            .ok_or(ErrorCode::MathOverflow)?
+            / (BPS_DENOMINATOR * SECONDS_PER_YEAR);
+        let flat = principal * (origination_bps as u128) / BPS_DENOMINATOR;
+        self.min_interest = u64::try_from(period.max(flat)).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        Ok(())
+    }
+
+    /// Records `paid` interest against the minimum-interest floor.
+    pub fn apply_interest_paid(&mut self, paid: u64) {
+        self.min_interest = self.min_interest.saturating_sub(paid);
+    }
+
+    /// Books a partial payment of `amount` at `now` and returns its
+    /// `(principal, interest)` split. Interest is paid first, up to what a
+    /// payoff would charge, so the minimum-interest floor is covered before
+    /// any principal. Fails when `amount` would settle the loan.
+    pub fn apply_partial_payment(&mut self, amount: u64, now: i64) -> Result<(u64, u64)> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount < self.amount_due(now)?, ErrorCode::PaymentCoversBalance);
+
+        let split = self.book_payment(amount, now)?;
+        self.schedule_paid = self.schedule_paid.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(split)
+    }
+
+    /// Books the winning bid of a settled auction toward the amount due and
+    /// returns its `(principal, interest, surplus)` split. Principal and
+    /// interest go to the current lender, the surplus to the borrower.
+    pub fn apply_auction_proceeds(&mut self, amount: u64, now: i64) -> Result<(u64, u64, u64)> {
+        let to_debt = amount.min(self.amount_due(now)?);
+        let (to_principal, to_interest) = self.book_payment(to_debt, now)?;
+        self.auction_proceeds = self.auction_proceeds.checked_add(to_debt).ok_or(ErrorCode::MathOverflow)?;
+        Ok((to_principal, to_interest, amount - to_debt))
+    }
+
+    /// Marks `domain` as auctioned (or returned) on its own.
+    pub fn mark_lot(&mut self, domain: &Pubkey) -> Result<()> {
+        let bit = self.domain_bit(domain).ok_or(ErrorCode::BasketMismatch)?;
+        require!(self.auction_lot_mask & bit == 0, ErrorCode::BasketMismatch);
+        self.auction_lot_mask |= bit;
+        Ok(())
+    }
+
+    /// Domains not yet auctioned or returned one at a time, primary first.
+    pub fn unsold_lots(&self) -> impl Iterator<Item = &Pubkey> {
+        std::iter::once(&self.domain_registry)
+            .chain(self.basket())
+            .filter(|domain| self.domain_bit(domain).is_some_and(|bit| self.auction_lot_mask & bit == 0))
+    }
+
+    /// Books `amount`, at most the amount due, as interest first and then
+    /// principal. Returns the `(principal, interest)` split.
+    fn book_payment(&mut self, amount: u64, now: i64) -> Result<(u64, u64)> {
+        let interest = self.accrued_interest(now)?;
+        let to_interest = amount.min(self.payoff_interest(now)?);
+        let to_principal = amount - to_interest;
+
+        self.unpaid_interest = interest.saturating_sub(to_interest);
+        self.apply_interest_paid(to_interest);
+        self.principal_amount = self.principal_amount.checked_sub(to_principal).ok_or(ErrorCode::MathOverflow)?;
+        self.repaid_amount = self.repaid_amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.last_update_ts = now;
+        Ok((to_principal, to_interest))
+    }
+
+    /// Interest owed on a full payoff at `now`: accrued interest, but never
+    /// less than the remaining minimum.
+    pub fn payoff_interest(&self, now: i64) -> Result<u64> {
+        Ok(self.accrued_interest(now)?.max(self.min_interest))
+    }
+
+    /// Unpaid interest plus simple interest accrued on the outstanding
+    /// principal since the last payment (or since the loan started).
+    pub fn accrued_interest(&self, now: i64) -> Result<u64> {
+        let from = self.last_update_ts.max(self.start_ts);
+        let elapsed = now.saturating_sub(from).max(0) as u128;
+        let interest = (self.principal_amount as u128)
+            .checked_mul(self.apr_bps as u128)
+            .and_then(|v| v.checked_mul(elapsed))
+            .ok_or(ErrorCode::MathOverflow)?
+            / (BPS_DENOMINATOR * SECONDS_PER_YEAR);
+        u64::try_from(interest)
+            .ok()
+            .and_then(|v| v.checked_add(self.unpaid_interest))
+            .ok_or(error!(ErrorCode::MathOverflow))
+    }
+
+    /// Starts an installment schedule of `count` equal payments, one every
+    /// `period` seconds from `start_ts`. The amount amortizes the principal:
+    /// each payment covers the period's interest on the outstanding balance
+    /// and the rest pays down principal, rounded up so the last payment
+    /// clears the loan.
+    pub fn start_schedule(&mut self, period: i64, count: u16) -> Result<()> {
+        self.installment_period = period;
+        self.installment_count = count;
+        self.schedule_paid = 0;
+        self.installment_amount = 0;
+        if count == 0 {
+            return Ok(());
+        }
+        let principal = self.principal_amount as u128;
+        let rate = (self.apr_bps as u128)
+            .checked_mul(period.max(0) as u128)
+            .and_then(|v| v.checked_mul(WAD))
+            .ok_or(ErrorCode::MathOverflow)?
+            / (BPS_DENOMINATOR * SECONDS_PER_YEAR);
+        let per_period = if rate == 0 {
+            principal.div_ceil(count as u128)
+        } else {
+            // payment = principal * rate * g / (g - 1), with g = (1 + rate)^count
+            let growth = wad_pow(WAD + rate, count)?;
+            let factor = rate.checked_mul(growth).ok_or(ErrorCode::MathOverflow)? / (growth - WAD);
+            principal.checked_mul(factor).ok_or(ErrorCode::MathOverflow)?.div_ceil(WAD)
+        };
+        self.installment_amount = u64::try_from(per_period).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        Ok(())
+    }
+
+    /// True when fewer installments have been paid than have fallen due.
+    pub fn installment_overdue(&self, now: i64) -> bool {
+        if self.installment_count == 0 || self.installment_period <= 0 {
+            return false;
+        }
+        let periods = (now.saturating_sub(self.start_ts) / self.installment_period).max(0) as u128;
+        let periods = periods.min(self.installment_count as u128);
+        (self.schedule_paid as u128) < periods * (self.installment_amount as u128)
+    }
+
+    /// Principal plus payoff interest owed at `now`.
+    pub fn amount_due(&self, now: i64) -> Result<u64> {
+        self.principal_amount
+            .checked_add(self.payoff_interest(now)?)
+            .ok_or(error!(ErrorCode::MathOverflow))
+    }
+}
+
+/// Fixed-point scale of the installment amortization math.
+const WAD: u128 = 1_000_000_000_000_000_000;
+
+/// `base^exp` for a `WAD`-scaled `base`, by repeated squaring.
+fn wad_pow(mut base: u128, mut exp: u16) -> Result<u128> {
+    let mut result = WAD;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.checked_mul(base).ok_or(ErrorCode::MathOverflow)? / WAD;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = base.checked_mul(base).ok_or(ErrorCode::MathOverflow)? / WAD;
+        }
+    }
+    Ok(result)
+}
+
+/// Content hashes (see `sns::content_hash`) of the domain registry and the
+/// record accounts escrowed with it. An all-zero `header_hash` means no
+/// snapshot was taken.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RecordSnapshot {
+    pub header_hash: [u8; 32],
+    pub record_count: u8,
+    pub records: [Pubkey; MAX_SNAPSHOT_RECORDS],
+    pub record_hashes: [[u8; 32]; MAX_SNAPSHOT_RECORDS],
+}
+
+impl RecordSnapshot {
+    pub fn is_taken(&self) -> bool {
+        self.header_hash != [0; 32]
+    }
+
+    /// Snapshotted record accounts.
+    pub fn records(&self) -> &[Pubkey] {
+        &self.records[..self.record_count as usize]
+    }
+
+    pub fn push(&mut self, record: Pubkey, hash: [u8; 32]) -> Result<()> {
+        let at = self.record_count as usize;
+        require!(at < MAX_SNAPSHOT_RECORDS, ErrorCode::TooManySnapshotRecords);
+        require!(!self.records().contains(&record), ErrorCode::RecordMismatch);
+        self.records[at] = record;
+        self.record_hashes[at] = hash;
+        self.record_count += 1;
+        Ok(())
+    }
+
+    /// Replaces the hash of `record`, adding it to the snapshot if it is not
+    /// part of it yet.
+    pub fn track(&mut self, record: Pubkey, hash: [u8; 32]) -> Result<()> {
+        match self.records().iter().position(|r| *r == record) {
+            Some(at) => {
+                self.record_hashes[at] = hash;
+                Ok(())
+            }
+            None => self.push(record, hash),
+        }
+    }
+}
+
 #[account]
 pub struct OfferAccount {
     pub lender: Pubkey,
-    pub principal: u64,
+    pub total_budget: u64,
+    pub max_per_loan: u64,
+    pub remaining_capacity: u64,
+    pub active_loans: u32,
     pub apr_bps: u16,
     pub duration_seconds: i64,
     pub offer_expiry: i64,
+    pub max_rollovers: u8,
+    pub installment_period: i64,
+    pub installment_count: u16,
+    pub callable: bool,
+    pub notice_period_seconds: i64,
+    pub min_interest_seconds: i64,
+    pub origination_interest_bps: u16,
     pub is_active: bool,
     pub nonce: u64,
     pub bump: u8,
 }
 
+impl OfferAccount {
+    /// Books a new draw of `amount` against this offer.
+    pub fn draw(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0 && amount <= self.max_per_loan, ErrorCode::OfferCapacityExceeded);
+        self.remaining_capacity = self
+            .remaining_capacity
+            .checked_sub(amount)
+            .ok_or(ErrorCode::OfferCapacityExceeded)?;
+        self.active_loans = self.active_loans.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        if self.remaining_capacity == 0 {
+            self.is_active = false;
+        }
+        Ok(())
+    }
+
+    /// Releases a loan backed by this offer once it is repaid or settled.
+    pub fn release_loan(&mut self) -> Result<()> {
+        self.active_loans = self.active_loans.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct LoanRequest {
+    pub borrower: Pubkey,
+    pub loan: Pubkey,
+    /// Token mint the loan must be funded in.
+    pub mint: Pubkey,
+    pub principal: u64,
+    pub max_apr_bps: u16,
+    pub duration_seconds: i64,
+    pub expiry: i64,
+    pub bump: u8,
+}
+
+/// Per-lender settings. Offers and signed quotes with a nonce below
+/// `min_nonce` can no longer be taken.
+#[account]
+pub struct LenderProfile {
+    pub lender: Pubkey,
+    pub min_nonce: u64,
+    pub bump: u8,
+}
+
+impl LenderProfile {
+    /// Reads `min_nonce` from a lender profile PDA, treating a profile that
+    /// was never created as 0. Callers must have checked the PDA seeds.
+    pub fn min_nonce_of(info: &AccountInfo) -> Result<u64> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(0);
+        }
+        let profile = LenderProfile::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        Ok(profile.min_nonce)
+    }
+}
+
+/// Marks a signed-offer nonce as consumed, either by a borrower taking the
+/// offer or by the lender invalidating it.
+#[account]
+pub struct UsedNonce {
+    pub lender: Pubkey,
+    pub nonce: u64,
+    pub bump: u8,
+}
+
+/// Sale listing of a P2P loan's creditor rights.
+#[account]
+pub struct ClaimListing {
+    pub loan: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub bump: u8,
+}
+
 #[account]
 pub struct AuctionAccount {
     pub loan: Pubkey,
@@ -68,6 +529,11 @@ pub struct AuctionAccount {
     pub end_price: u64,
     pub min_bid: u64,
     pub status: AuctionStatus,
+    /// Domain sold by a per-domain auction; default when the auction sells
+    /// all of the loan's domains as one lot.
+    pub domain: Pubkey,
+    /// Mint of the loan; bids are held in an auction vault of this mint.
+    pub mint: Pubkey,
     pub bump: u8,
 }
 
@@ -92,4 +558,309 @@ pub enum LoanType {
 pub enum AuctionStatus {
     Live,
     Ended,
+    Settled,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+    const YEAR: i64 = 365 * DAY;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    /// Active bullet loan of `principal` at `apr_bps`, started at 0.
+    fn loan(principal: u64, apr_bps: u16) -> LoanAccount {
+        LoanAccount {
+            borrower: key(1),
+            domain_registry: key(2),
+            seed_domain: key(2),
+            escrow_pda: key(3),
+            principal_amount: principal,
+            repaid_amount: 0,
+            unpaid_interest: 0,
+            min_interest: 0,
+            apr_bps,
+            start_ts: 0,
+            due_ts: YEAR,
+            duration_seconds: YEAR,
+            installment_period: 0,
+            installment_count: 0,
+            installment_amount: 0,
+            schedule_paid: 0,
+            rollover_count: 0,
+            max_rollovers: 0,
+            callable: false,
+            notice_period_seconds: 0,
+            grace_end_ts: 0,
+            last_update_ts: 0,
+            status: LoanStatus::Active,
+            loan_type: LoanType::P2P,
+            lender_source: key(4),
+            creditor: Pubkey::default(),
+            mint: key(5),
+            claim_mint: Pubkey::default(),
+            claim_generation: 0,
+            record_payout: Pubkey::default(),
+            record_confirmed: false,
+            record_cleanup_mask: 0,
+            record_snapshot: RecordSnapshot::default(),
+            repay_delegate: Pubkey::default(),
+            basket: [Pubkey::default(); MAX_BASKET_DOMAINS],
+            basket_count: 0,
+            auction_lots: 0,
+            lots_settled: 0,
+            auction_lot_mask: 0,
+            auction_proceeds: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn accrues_simple_interest() {
+        let loan = loan(1_000_000_000, 1000);
+        assert_eq!(loan.accrued_interest(0).unwrap(), 0);
+        assert_eq!(loan.accrued_interest(YEAR).unwrap(), 100_000_000);
+        assert_eq!(loan.amount_due(YEAR).unwrap(), 1_100_000_000);
+    }
+
+    #[test]
+    fn accrues_from_last_payment_on_top_of_unpaid_interest() {
+        let mut loan = loan(1_000_000_000, 1000);
+        loan.last_update_ts = YEAR / 2;
+        loan.unpaid_interest = 7;
+        assert_eq!(loan.accrued_interest(YEAR).unwrap(), 50_000_007);
+        // No negative accrual before the last payment
+        assert_eq!(loan.accrued_interest(0).unwrap(), 7);
+    }
+
+    #[test]
+    fn payoff_interest_is_at_least_the_minimum() {
+        let mut loan = loan(1_000_000_000, 1000);
+        loan.set_min_interest(73 * DAY, 0).unwrap();
+        assert_eq!(loan.min_interest, 20_000_000);
+
+        assert_eq!(loan.payoff_interest(DAY).unwrap(), 20_000_000);
+        assert_eq!(loan.amount_due(DAY).unwrap(), 1_020_000_000);
+        assert_eq!(loan.payoff_interest(YEAR).unwrap(), 100_000_000);
+
+        // Interest paid counts toward the minimum
+        loan.apply_interest_paid(15_000_000);
+        assert_eq!(loan.payoff_interest(DAY).unwrap(), 5_000_000);
+    }
+
+    #[test]
+    fn partial_payment_covers_the_minimum_interest_first() {
+        let mut loan = loan(1_000, 0);
+        loan.min_interest = 100;
+
+        assert_eq!(loan.apply_partial_payment(1_050, 0).unwrap(), (950, 100));
+        assert_eq!(loan.principal_amount, 50);
+        assert_eq!(loan.min_interest, 0);
+        assert_eq!(loan.unpaid_interest, 0);
+        assert_eq!(loan.amount_due(0).unwrap(), 50);
+    }
+
+    #[test]
+    fn partial_payment_leaves_unpaid_interest() {
+        let mut loan = loan(1_000_000_000, 1000);
+        assert_eq!(loan.apply_partial_payment(40_000_000, YEAR).unwrap(), (0, 40_000_000));
+        assert_eq!(loan.unpaid_interest, 60_000_000);
+        assert_eq!(loan.repaid_amount, 40_000_000);
+        assert_eq!(loan.last_update_ts, YEAR);
+    }
+
+    #[test]
+    fn partial_payment_must_not_settle_the_loan() {
+        let mut loan = loan(1_000, 0);
+        loan.min_interest = 100;
+        assert!(loan.apply_partial_payment(1_100, 0).is_err());
+        assert!(loan.apply_partial_payment(0, 0).is_err());
+        assert_eq!(loan.principal_amount, 1_000);
+    }
+
+    #[test]
+    fn minimum_interest_uses_the_larger_floor() {
+        let mut loan = loan(1_000_000_000, 1000);
+        loan.set_min_interest(73 * DAY, 300).unwrap();
+        assert_eq!(loan.min_interest, 30_000_000);
+        loan.set_min_interest(0, 0).unwrap();
+        assert_eq!(loan.min_interest, 0);
+    }
+
+    #[test]
+    fn bullet_loan_has_no_schedule() {
+        let mut loan = loan(1_000_000_000, 1000);
+        loan.start_schedule(0, 0).unwrap();
+        assert_eq!(loan.installment_amount, 0);
+        assert!(!loan.installment_overdue(10 * YEAR));
+    }
+
+    #[test]
+    fn interest_free_schedule_splits_principal() {
+        let mut loan = loan(1_000, 0);
+        loan.start_schedule(30 * DAY, 3).unwrap();
+        assert_eq!(loan.installment_amount, 334);
+    }
+
+    #[test]
+    fn amortizing_schedule_pays_off_with_the_last_installment() {
+        let period = 30 * DAY;
+        let mut loan = loan(1_000_000_000, 1200);
+        loan.start_schedule(period, 12).unwrap();
+        let amount = loan.installment_amount;
+        // Level payment for 1,000 at 12% APR over twelve 30-day periods
+        assert!((88_700_000..88_800_000).contains(&amount), "{amount}");
+
+        for k in 1..12 {
+            let now = k * period;
+            assert!(amount < loan.amount_due(now).unwrap());
+            let interest = loan.accrued_interest(now).unwrap();
+            loan.principal_amount -= amount - interest;
+            loan.unpaid_interest = 0;
+            loan.last_update_ts = now;
+            loan.schedule_paid += amount;
+        }
+
+        // The last installment covers what is left, give or take rounding
+        let due = loan.amount_due(12 * period).unwrap();
+        assert!(amount >= due && amount - due < 100, "{amount} vs {due}");
+    }
+
+    #[test]
+    fn flags_missed_installments() {
+        let period = 30 * DAY;
+        let mut loan = loan(1_000_000_000, 1200);
+        loan.start_schedule(period, 3).unwrap();
+
+        assert!(!loan.installment_overdue(period - 1));
+        assert!(loan.installment_overdue(period));
+        loan.schedule_paid = loan.installment_amount;
+        assert!(!loan.installment_overdue(2 * period - 1));
+        assert!(loan.installment_overdue(2 * period));
+
+        // Never more than the scheduled count falls due
+        loan.schedule_paid = 3 * loan.installment_amount;
+        assert!(!loan.installment_overdue(10 * period));
+    }
+
+    #[test]
+    fn snapshot_holds_distinct_records() {
+        let mut snapshot = RecordSnapshot::default();
+        assert!(!snapshot.is_taken());
+        snapshot.header_hash = [9; 32];
+        assert!(snapshot.is_taken());
+
+        snapshot.push(key(1), [1; 32]).unwrap();
+        assert!(snapshot.push(key(1), [2; 32]).is_err());
+        for byte in 2..=MAX_SNAPSHOT_RECORDS as u8 {
+            snapshot.push(key(byte), [byte; 32]).unwrap();
+        }
+        assert!(snapshot.push(key(99), [0; 32]).is_err());
+        assert_eq!(snapshot.records().len(), MAX_SNAPSHOT_RECORDS);
+        assert_eq!(snapshot.record_hashes[0], [1; 32]);
+    }
+
+    #[test]
+    fn track_updates_or_adds_a_record() {
+        let mut snapshot = RecordSnapshot::default();
+        snapshot.push(key(1), [1; 32]).unwrap();
+
+        snapshot.track(key(1), [7; 32]).unwrap();
+        assert_eq!(snapshot.records(), &[key(1)]);
+        assert_eq!(snapshot.record_hashes[0], [7; 32]);
+
+        snapshot.track(key(2), [8; 32]).unwrap();
+        assert_eq!(snapshot.records(), &[key(1), key(2)]);
+        assert_eq!(snapshot.record_hashes[1], [8; 32]);
+    }
+
+    #[test]
+    fn basket_holds_distinct_domains() {
+        let mut loan = loan(0, 0);
+        assert_eq!(loan.domain_count(), 1);
+        assert!(loan.add_to_basket(key(2)).is_err());
+
+        for byte in 10..10 + MAX_BASKET_DOMAINS as u8 {
+            loan.add_to_basket(key(byte)).unwrap();
+        }
+        assert!(loan.add_to_basket(key(10)).is_err());
+        assert!(loan.add_to_basket(key(99)).is_err());
+        assert_eq!(loan.domain_count() as usize, 1 + MAX_BASKET_DOMAINS);
+        assert!(loan.holds_domain(&key(11)));
+        assert!(!loan.holds_domain(&key(99)));
+    }
+
+    #[test]
+    fn tracks_record_cleanup_per_domain() {
+        let mut loan = loan(0, 0);
+        loan.add_to_basket(key(10)).unwrap();
+        loan.add_to_basket(key(11)).unwrap();
+        assert_eq!(loan.domain_bit(&key(2)), Some(1));
+        assert_eq!(loan.domain_bit(&key(11)), Some(4));
+        assert_eq!(loan.domain_bit(&key(99)), None);
+
+        loan.record_cleanup_mask = 4;
+        assert!(loan.needs_record_cleanup(&key(11)));
+        assert!(!loan.needs_record_cleanup(&key(2)));
+        assert!(!loan.needs_record_cleanup(&key(10)));
+        assert!(!loan.needs_record_cleanup(&key(99)));
+    }
+
+    #[test]
+    fn replaces_primary_or_basket_domain() {
+        let mut loan = loan(0, 0);
+        loan.add_to_basket(key(10)).unwrap();
+
+        loan.replace_domain(&key(2), key(20)).unwrap();
+        assert_eq!(loan.domain_registry, key(20));
+        assert_eq!(loan.seed_domain, key(2));
+
+        loan.replace_domain(&key(10), key(21)).unwrap();
+        assert_eq!(loan.basket(), &[key(21)]);
+        assert_eq!(loan.domain_count(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_or_held_domains() {
+        let mut loan = loan(0, 0);
+        loan.add_to_basket(key(10)).unwrap();
+
+        assert!(loan.replace_domain(&key(99), key(20)).is_err());
+        assert!(loan.replace_domain(&key(2), key(10)).is_err());
+        assert!(loan.replace_domain(&key(10), key(2)).is_err());
+        assert_eq!(loan.domain_registry, key(2));
+        assert_eq!(loan.basket(), &[key(10)]);
+    }
+
+    #[test]
+    fn lot_auctions_stop_once_the_debt_is_covered() {
+        let mut loan = loan(1_000, 0);
+        loan.min_interest = 100;
+        loan.add_to_basket(key(10)).unwrap();
+        loan.add_to_basket(key(11)).unwrap();
+
+        // Lot 1 of 3 sells for more than the whole debt
+        loan.mark_lot(&key(10)).unwrap();
+        assert!(loan.mark_lot(&key(10)).is_err());
+        assert_eq!(loan.apply_auction_proceeds(1_500, 0).unwrap(), (1_000, 100, 400));
+        assert_eq!(loan.amount_due(0).unwrap(), 0);
+        assert_eq!(loan.auction_proceeds, 1_100);
+        assert_eq!(loan.repaid_amount, 1_100);
+
+        // The other two domains go back to the borrower
+        assert_eq!(loan.unsold_lots().copied().collect::<Vec<_>>(), vec![key(2), key(11)]);
+        assert_eq!(loan.apply_auction_proceeds(50, 0).unwrap(), (0, 0, 50));
+    }
+
+    #[test]
+    fn lot_proceeds_below_the_debt_all_go_to_the_lender() {
+        let mut loan = loan(1_000_000_000, 1000);
+        assert_eq!(loan.apply_auction_proceeds(300_000_000, YEAR).unwrap(), (200_000_000, 100_000_000, 0));
+        assert_eq!(loan.principal_amount, 800_000_000);
+        assert_eq!(loan.amount_due(YEAR).unwrap(), 800_000_000);
+    }
 }
@@ -60,13 +60,27 @@ describe("solname-credit p2p tests", () => {
   });
 
   it("Creates a P2P offer", async () => {
-    const principal = new anchor.BN(100000000); // 100 USDC
+    const totalBudget = new anchor.BN(100000000); // 100 USDC
+    const maxPerLoan = new anchor.BN(25000000); // 25 USDC per borrower
     const aprBps = 1000; // 10%
     const durationSeconds = new anchor.BN(86400 * 30); // 30 days
     const offerExpiry = new anchor.BN(Math.floor(Date.now() / 1000) + 86400 * 7); // Expires in 7 days
 
     await program.methods
-      .createOffer(nonce, principal, aprBps, durationSeconds, offerExpiry)
+      .createOffer(nonce, {
+        totalBudget,
+        maxPerLoan,
+        aprBps,
+        durationSeconds,
+        offerExpiry,
+        maxRollovers: 1,
+        installmentPeriod: new anchor.BN(86400 * 10), // 3 payments, every 10 days
+        installmentCount: 3,
+        callable: false,
+        noticePeriodSeconds: new anchor.BN(0),
+        minInterestSeconds: new anchor.BN(86400 * 3),
+        originationInterestBps: 0,
+      })
       .accounts({
         lender: lender.publicKey,
         offerAccount: offerAccountPda,
@@ -82,7 +96,14 @@ describe("solname-credit p2p tests", () => {
 
     const offer = await program.account.offerAccount.fetch(offerAccountPda);
     assert.ok(offer.lender.equals(lender.publicKey));
-    assert.ok(offer.principal.eq(principal));
+    assert.ok(offer.totalBudget.eq(totalBudget));
+    assert.ok(offer.maxPerLoan.eq(maxPerLoan));
+    assert.ok(offer.remainingCapacity.eq(totalBudget));
+    assert.equal(offer.activeLoans, 0);
+    assert.equal(offer.maxRollovers, 1);
+    assert.equal(offer.installmentCount, 3);
+    assert.equal(offer.callable, false);
+    assert.ok(offer.minInterestSeconds.eq(new anchor.BN(86400 * 3)));
     assert.ok(offer.isActive);
   });
 
@@ -108,4 +129,64 @@ describe("solname-credit p2p tests", () => {
         assert.ok(e.message.includes("Account does not exist"));
     }
   });
+
+  it("Invalidates a signed offer nonce", async () => {
+    const signedNonce = new anchor.BN(42);
+    const [usedNoncePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("nonce"), lender.publicKey.toBuffer(), signedNonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .invalidateNonce(signedNonce)
+      .accounts({
+        lender: lender.publicKey,
+        usedNonce: usedNoncePda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lender])
+      .rpc();
+
+    const used = await program.account.usedNonce.fetch(usedNoncePda);
+    assert.ok(used.lender.equals(lender.publicKey));
+    assert.ok(used.nonce.eq(signedNonce));
+
+    // A second invalidation (or a borrower taking the quote) must fail
+    let reused = true;
+    try {
+        await program.methods
+          .invalidateNonce(signedNonce)
+          .accounts({
+            lender: lender.publicKey,
+            usedNonce: usedNoncePda,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .signers([lender])
+          .rpc();
+    } catch (e) {
+        reused = false;
+    }
+    assert.isFalse(reused, "Nonce should not be reusable");
+  });
+
+  it("Raises the lender minimum nonce", async () => {
+    const [lenderProfilePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lender"), lender.publicKey.toBuffer()],
+      program.programId
+    );
+    const minNonce = nonce.addn(1);
+
+    await program.methods
+      .setMinNonce(minNonce)
+      .accounts({
+        lender: lender.publicKey,
+        lenderProfile: lenderProfilePda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lender])
+      .rpc();
+
+    const profile = await program.account.lenderProfile.fetch(lenderProfilePda);
+    assert.ok(profile.minNonce.eq(minNonce));
+  });
 });